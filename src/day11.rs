@@ -14,7 +14,6 @@ pub fn part_two() -> u64 {
 
 #[derive(Clone)]
 struct Monkey {
-  name: String,
   items: Vec<u64>,
   operator: String,
   operand: String,
@@ -23,26 +22,34 @@ struct Monkey {
   dest_if_false: u64,
 }
 
-impl Monkey {
-  fn clone_new_items(self: &Self, new_items: &Vec<u64>) -> Monkey {
-    Monkey {
-      name: self.name.to_string(),
-      items: new_items.clone(),
-      operator: self.operator.to_string(),
-      operand: self.operand.to_string(),
-      test_divisible_by: self.test_divisible_by.clone(),
-      dest_if_true: self.dest_if_true.clone(),
-      dest_if_false: self.dest_if_false.clone(),
-    }
-  }
+// Snapshot of every monkey after a given round. `throws[from][to]` counts how
+// many items monkey `from` has thrown to monkey `to` so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundReport {
+  pub round: u64,
+  pub items: Vec<Vec<u64>>,
+  pub inspections: Vec<u64>,
+  pub throws: Vec<Vec<u64>>,
+}
+
+pub fn report(rounds: u64, worry_divisor: u64, report_after: &[u64]) -> Vec<RoundReport> {
+  let lines = read_chunks("day11.txt", "Monkey");
+  simulate(&lines, rounds, worry_divisor, report_after)
+}
+
+fn solve_one(groups: &[String], rounds: u64, worry_divisor: u64) -> u64 {
+  let reports = simulate(groups, rounds, worry_divisor, &[rounds]);
+  let mut inspections = reports.last().unwrap().inspections.clone();
+
+  inspections.sort();
+  inspections.iter().rev().take(2).product()
 }
 
-fn solve_one(groups: &Vec<String>, rounds: u64, worry_divisor: u64) -> u64 {
-  let mut monkeys: Vec<Monkey> = groups
+fn parse_monkeys(groups: &[String]) -> Vec<Monkey> {
+  groups
     .iter()
     .map(|g| {
       let lines = g.split("\n").collect::<Vec<&str>>();
-      let name = lines.get(0).unwrap().to_string();
       let items: Vec<u64> = lines
         .get(1).unwrap()
         .split(':')
@@ -92,7 +99,6 @@ fn solve_one(groups: &Vec<String>, rounds: u64, worry_divisor: u64) -> u64 {
         .parse().unwrap();
 
       Monkey {
-        name,
         items,
         operator,
         operand,
@@ -101,12 +107,16 @@ fn solve_one(groups: &Vec<String>, rounds: u64, worry_divisor: u64) -> u64 {
         dest_if_false,
       }
     })
-    .collect();
+    .collect()
+}
 
-  let mut inspections = vec![];
-  for _ in 0..monkeys.len() {
-    inspections.push(0);
-  }
+// Runs the monkeys for `rounds` rounds and takes a snapshot after each round
+// listed in `report_after` (1-based, round 0 is the starting state)
+pub fn simulate(groups: &[String], rounds: u64, worry_divisor: u64, report_after: &[u64]) -> Vec<RoundReport> {
+  let mut monkeys = parse_monkeys(groups);
+  let mut inspections = vec![0; monkeys.len()];
+  let mut throws = vec![vec![0; monkeys.len()]; monkeys.len()];
+  let mut reports = vec![];
 
   // For part 2 our numbers are going to grow tremendously fast so we need to
   // hold them down in a way that we didn't for part 1. Calculate the product of
@@ -117,14 +127,25 @@ fn solve_one(groups: &Vec<String>, rounds: u64, worry_divisor: u64) -> u64 {
     .map(|m| m.test_divisible_by)
     .product();
 
+  let snapshot = |round: u64, monkeys: &[Monkey], inspections: &[u64], throws: &[Vec<u64>]| RoundReport {
+    round,
+    items: monkeys.iter().map(|m| m.items.clone()).collect(),
+    inspections: inspections.to_vec(),
+    throws: throws.to_vec(),
+  };
+
+  if report_after.contains(&0) {
+    reports.push(snapshot(0, &monkeys, &inspections, &throws));
+  }
 
-  for _round in 0..rounds {
+  for round in 1..=rounds {
     for curr_monkey_id in 0..monkeys.len() {
-      let curr_monkey = monkeys.get(curr_monkey_id).unwrap().clone();
-      for item in &curr_monkey.items {
-        inspections[curr_monkey_id] = inspections.get(curr_monkey_id).unwrap() + 1;
+      let items = std::mem::take(&mut monkeys[curr_monkey_id].items);
+      for item in items {
+        let curr_monkey = &monkeys[curr_monkey_id];
+        inspections[curr_monkey_id] += 1;
         let worry_operand = if curr_monkey.operand == "old" {
-          item.to_owned()
+          item
         } else {
           curr_monkey.operand.parse::<u64>().unwrap()
         };
@@ -138,34 +159,51 @@ fn solve_one(groups: &Vec<String>, rounds: u64, worry_divisor: u64) -> u64 {
         // part 1 this should be 3, part 2 it's 1 (ie: your worry doesn't reduce)
         let worry_operand = interim_worry_value / worry_divisor;
 
-        let updated_dest_monkey;
-        let dest_monkey_id;
-        if worry_operand % curr_monkey.test_divisible_by == 0 {
-          dest_monkey_id = curr_monkey.dest_if_true as usize;
-          let dest_monkey = monkeys.get(dest_monkey_id).unwrap().clone();
-          let mut new_items = dest_monkey.items.clone();
-          new_items.push(worry_operand % bounding_mod_value);
-          updated_dest_monkey = dest_monkey.clone_new_items(&new_items);
+        let dest_monkey_id = if worry_operand.is_multiple_of(curr_monkey.test_divisible_by) {
+          curr_monkey.dest_if_true as usize
         } else {
-          dest_monkey_id = curr_monkey.dest_if_false as usize;
-          let dest_monkey = monkeys.get(dest_monkey_id).unwrap().clone();
-          let mut new_items = dest_monkey.items.clone();
-          new_items.push(worry_operand % bounding_mod_value);
-          updated_dest_monkey = dest_monkey.clone_new_items(&new_items);
-        }
-        monkeys[dest_monkey_id] = updated_dest_monkey;
-        monkeys[curr_monkey_id] = curr_monkey.clone_new_items(&vec![]);
+          curr_monkey.dest_if_false as usize
+        };
+        throws[curr_monkey_id][dest_monkey_id] += 1;
+        monkeys[dest_monkey_id].items.push(worry_operand % bounding_mod_value);
       }
     }
+
+    if report_after.contains(&round) {
+      reports.push(snapshot(round, &monkeys, &inspections, &throws));
+    }
   }
 
-  inspections.sort();
-  inspections.iter().rev().take(2).product()
+  reports
+}
+
+// One row per monkey per reported round, items are space separated so the
+// column stays a single CSV field
+pub fn reports_to_csv(reports: &[RoundReport]) -> String {
+  let monkey_count = reports.first().map(|r| r.inspections.len()).unwrap_or(0);
+  let mut header = vec!["round".to_string(), "monkey".to_string(), "inspections".to_string(), "items".to_string()];
+  header.extend((0..monkey_count).map(|id| format!("throws_to_{}", id)));
+
+  let mut lines = vec![header.join(",")];
+  for report in reports {
+    for monkey_id in 0..monkey_count {
+      let mut row = vec![
+        report.round.to_string(),
+        monkey_id.to_string(),
+        report.inspections[monkey_id].to_string(),
+        report.items[monkey_id].iter().map(|i| i.to_string()).collect::<Vec<String>>().join(" "),
+      ];
+      row.extend(report.throws[monkey_id].iter().map(|t| t.to_string()));
+      lines.push(row.join(","));
+    }
+  }
+
+  lines.join("\n") + "\n"
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::day11::solve_one;
+  use crate::day11::{reports_to_csv, simulate, solve_one};
 
   #[test]
   fn test_sample_1() {
@@ -181,6 +219,39 @@ mod tests {
     assert_eq!(result, 2713310158);
   }
 
+  #[test]
+  fn test_round_reports() {
+    let inputs = get_inputs();
+    let reports = simulate(&inputs, 20, 3, &[1, 20]);
+    assert_eq!(reports.len(), 2);
+
+    assert_eq!(reports[0].round, 1);
+    assert_eq!(reports[0].items, vec![
+      vec![20, 23, 27, 26],
+      vec![2080, 25, 167, 207, 401, 1046],
+      vec![],
+      vec![],
+    ]);
+
+    assert_eq!(reports[1].inspections, vec![101, 95, 7, 105]);
+    let total_throws: u64 = reports[1].throws.iter().flatten().sum();
+    assert_eq!(total_throws, reports[1].inspections.iter().sum::<u64>());
+    // Monkey 2 only ever throws to monkeys 1 and 3
+    assert_eq!(reports[1].throws[2][0], 0);
+    assert_eq!(reports[1].throws[2][2], 0);
+  }
+
+  #[test]
+  fn test_reports_to_csv() {
+    let inputs = get_inputs();
+    let reports = simulate(&inputs, 1, 3, &[1]);
+    let csv = reports_to_csv(&reports);
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(lines[0], "round,monkey,inspections,items,throws_to_0,throws_to_1,throws_to_2,throws_to_3");
+    assert_eq!(lines[1], "1,0,2,20 23 27 26,0,0,0,2");
+    assert_eq!(lines.len(), 5);
+  }
+
   fn get_inputs() -> Vec<String> {
     let raw_input =
       "Monkey 0:
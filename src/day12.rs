@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use crate::day08::Point;
 use crate::utils::read_chunks;
 
@@ -9,7 +10,7 @@ pub fn part_one() -> u64 {
   let dest = find_point_for_value(&grid, 'E');
   let grid = convert_grid_values_to_numeric(&grid);
 
-  solve_one(&grid, &start, &dest)
+  solve_one(&grid, &start, &dest).expect("No path to the summit")
 }
 
 pub fn part_two() -> u64 {
@@ -18,88 +19,290 @@ pub fn part_two() -> u64 {
   let dest = find_point_for_value(&grid, 'E');
   let grid = convert_grid_values_to_numeric(&grid);
 
-  solve_two(&grid, &dest)
+  solve_two(&grid, &dest).expect("No path to the summit from any start")
 }
 
-fn solve_one(grid: &HashMap<Point, u64>, start: &Point, dest: &Point) -> u64 {
-  let neighbors = vec![
-    Point { x: 1, y: 0 },
-    Point { x: -1, y: 0 },
-    Point { x: 0, y: 1 },
-    Point { x: 0, y: -1 },
-  ];
+// Cheapest route from S to E under a custom set of rules, drawn onto the
+// height map
+pub fn render_least_cost_route(rules: &ClimbRules) -> Option<(u64, String)> {
+  let lines = read_chunks("day12.txt", "\n");
+  let char_grid = lines_to_grid_char_val(&lines);
+  let start = find_point_for_value(&char_grid, 'S');
+  let dest = find_point_for_value(&char_grid, 'E');
+  let grid = convert_grid_values_with_rules(&char_grid, rules);
+
+  let (cost, path) = least_cost_route(&grid, &start, &dest, rules)?;
+  Some((cost, render_path(&char_grid, &path)))
+}
+
+// Draws the shortest route from S to E onto the height map
+pub fn render_shortest_path() -> Option<String> {
+  let lines = read_chunks("day12.txt", "\n");
+  let char_grid = lines_to_grid_char_val(&lines);
+  let start = find_point_for_value(&char_grid, 'S');
+  let dest = find_point_for_value(&char_grid, 'E');
+  let grid = convert_grid_values_to_numeric(&char_grid);
+
+  let path = shortest_path(&grid, &start, &dest)?;
+  Some(render_path(&char_grid, &path))
+}
+
+const NEIGHBORS: [Point; 4] = [
+  Point { x: 1, y: 0 },
+  Point { x: -1, y: 0 },
+  Point { x: 0, y: 1 },
+  Point { x: 0, y: -1 },
+];
+
+fn solve_one(grid: &HashMap<Point, u64>, start: &Point, dest: &Point) -> Option<u64> {
+  distances_to(grid, dest).get(start).copied()
+}
+
+// A single search backwards from the summit finds the distance to every
+// possible start at once, so we just pick the closest lowest point
+fn solve_two(grid: &HashMap<Point, u64>, dest: &Point) -> Option<u64> {
+  let distances = distances_to(grid, dest);
+  grid
+    .iter()
+    .filter(|(_, height)| **height == 1)
+    .filter_map(|(p, _)| distances.get(p).copied())
+    .min()
+}
+
+// BFS outwards from `dest` following the climbing rule in reverse, so every
+// point in the result can reach `dest` in the stored number of steps
+fn distances_to(grid: &HashMap<Point, u64>, dest: &Point) -> HashMap<Point, u64> {
   let mut to_visit: VecDeque<Point> = VecDeque::new();
-  let mut visited: HashSet<Point> = HashSet::new();
   let mut distance: HashMap<Point, u64> = HashMap::new();
 
-  to_visit.push_back(start.to_owned());
-  distance.insert(start.to_owned(), 0);
+  to_visit.push_back(*dest);
+  distance.insert(*dest, 0);
 
-  while !to_visit.is_empty() {
-    let current = to_visit.pop_front().unwrap();
-    let current_distance = *distance.get(&current).unwrap();
-    if current == *dest {
-      return *distance.get(&current).unwrap();
-    }
-
-    if visited.contains(&current) {
-      continue;
-    }
-    for n in &neighbors {
-      let possible = current + *n;
-      if visited.contains(&possible) || to_visit.contains(&&possible) {
-        continue;
-      }
-      if !move_allowed(grid, &current, &possible) {
+  while let Some(current) = to_visit.pop_front() {
+    let current_distance = distance[&current];
+    for n in NEIGHBORS {
+      let previous = current + n;
+      if distance.contains_key(&previous) || !grid.contains_key(&previous) || !move_allowed(grid, &previous, &current) {
         continue;
       }
 
-      distance.insert(possible.to_owned(), current_distance + 1);
-      to_visit.push_back(possible);
-      visited.insert(current);
+      distance.insert(previous, current_distance + 1);
+      to_visit.push_back(previous);
     }
   }
 
-  // Failsafe here for part 2 to be longer than any possible route could be in
-  // case there's a start that isn't "solvable" without doubling back over the path
-  (grid.len() + 1) as u64
+  distance
 }
 
-fn solve_two(grid: &HashMap<Point, u64>, dest: &Point) -> u64 {
-  grid
-    .iter()
-    .filter(|kv| *(*kv).1 == 1)
-    .map(|kv| solve_one(grid, kv.0, dest))
-    .min()
-    .expect("Really should have found at least one path!")
+// Every point along one shortest route, including both `start` and `dest`
+pub fn shortest_path(grid: &HashMap<Point, u64>, start: &Point, dest: &Point) -> Option<Vec<Point>> {
+  let distances = distances_to(grid, dest);
+  let mut current = *start;
+  let mut remaining = *distances.get(&current)?;
+  let mut path = vec![current];
+
+  while remaining > 0 {
+    current = NEIGHBORS
+      .iter()
+      .map(|n| current + *n)
+      .find(|next| distances.get(next) == Some(&(remaining - 1)) && move_allowed(grid, &current, next))
+      .expect("Distances should always lead one step closer");
+    remaining -= 1;
+    path.push(current);
+  }
+
+  Some(path)
 }
 
-fn move_allowed(grid: &HashMap<Point, u64>, point_at: &Point, point_next: &Point) -> bool {
-  if !grid.contains_key(point_next) {
-    return false;
+// Same style as the puzzle description: each step is marked with the
+// direction taken out of it, the summit stays `E`, everything else keeps its
+// height letter
+pub fn render_path(grid: &HashMap<Point, char>, path: &[Point]) -> String {
+  let mut overlay: HashMap<Point, char> = HashMap::new();
+  for step in path.windows(2) {
+    let arrow = match (step[1].x - step[0].x, step[1].y - step[0].y) {
+      (1, 0) => '>',
+      (-1, 0) => '<',
+      (0, 1) => 'v',
+      (0, -1) => '^',
+      (1, 1) | (-1, -1) => '\\',
+      (1, -1) | (-1, 1) => '/',
+      _ => panic!("Path steps must be to an adjacent point"),
+    };
+    overlay.insert(step[0], arrow);
   }
 
-  let at = grid.get(point_at).unwrap();
-  let next = grid.get(point_next).unwrap();
+  let max_x = grid.keys().map(|p| p.x).max().unwrap_or(-1);
+  let max_y = grid.keys().map(|p| p.y).max().unwrap_or(-1);
+  (0..=max_y)
+    .map(|y| {
+      (0..=max_x)
+        .map(|x| {
+          let p = Point { x, y };
+          *overlay.get(&p).or_else(|| grid.get(&p)).unwrap_or(&' ')
+        })
+        .collect::<String>()
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
 
-  (*at as i64 - *next as i64) >= -1
+fn move_allowed(grid: &HashMap<Point, u64>, point_at: &Point, point_next: &Point) -> bool {
+  ClimbRules::default().move_allowed(grid, point_at, point_next)
 }
 
 fn convert_grid_values_to_numeric(grid: &HashMap<Point, char>) -> HashMap<Point, u64> {
   grid
     .iter()
-    .map(|t| (t.0.clone(), score_for_letter(t.1)))
+    .map(|t| (*t.0, score_for_letter(t.1)))
+    .collect()
+}
+
+pub fn convert_grid_values_with_rules(grid: &HashMap<Point, char>, rules: &ClimbRules) -> HashMap<Point, u64> {
+  grid
+    .iter()
+    .map(|t| (*t.0, rules.height_for_letter(t.1)))
     .collect()
 }
 
 fn score_for_letter(letter: &char) -> u64 {
-  if letter == &'E' {
-    26
-  } else if letter == &'S' {
-    1
-  } else {
-    *letter as u64 - 96
+  ClimbRules::default().height_for_letter(letter)
+}
+
+// What a single step costs: a flat amount plus something for each level of
+// height gained or lost. The puzzle is just `StepCost::steps()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepCost {
+  pub base: u64,
+  pub per_ascent: u64,
+  pub per_descent: u64,
+}
+
+impl StepCost {
+  pub fn steps() -> StepCost {
+    StepCost { base: 1, per_ascent: 0, per_descent: 0 }
+  }
+
+  pub fn total_climb() -> StepCost {
+    StepCost { base: 0, per_ascent: 1, per_descent: 0 }
+  }
+
+  pub fn total_elevation_change() -> StepCost {
+    StepCost { base: 0, per_ascent: 1, per_descent: 1 }
+  }
+
+  fn cost(&self, at: u64, next: u64) -> u64 {
+    if next >= at {
+      self.base + (next - at) * self.per_ascent
+    } else {
+      self.base + (at - next) * self.per_descent
+    }
+  }
+}
+
+// Which moves are allowed over the height map and what they cost. `None` for
+// max ascent/descent means there's no limit in that direction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClimbRules {
+  pub max_ascent: Option<u64>,
+  pub max_descent: Option<u64>,
+  pub diagonal: bool,
+  pub step_cost: StepCost,
+  pub start_height: u64,
+  pub end_height: u64,
+}
+
+impl Default for ClimbRules {
+  // The rules from the puzzle: up at most one, down any amount, S is `a` and
+  // E is `z`
+  fn default() -> Self {
+    ClimbRules {
+      max_ascent: Some(1),
+      max_descent: None,
+      diagonal: false,
+      step_cost: StepCost::steps(),
+      start_height: 1,
+      end_height: 26,
+    }
+  }
+}
+
+impl ClimbRules {
+  pub fn height_for_letter(&self, letter: &char) -> u64 {
+    match letter {
+      'S' => self.start_height,
+      'E' => self.end_height,
+      _ => *letter as u64 - 96,
+    }
+  }
+
+  pub fn neighbors(&self) -> Vec<Point> {
+    let mut neighbors = NEIGHBORS.to_vec();
+    if self.diagonal {
+      neighbors.extend([
+        Point { x: 1, y: 1 },
+        Point { x: 1, y: -1 },
+        Point { x: -1, y: 1 },
+        Point { x: -1, y: -1 },
+      ]);
+    }
+    neighbors
+  }
+
+  pub fn move_allowed(&self, grid: &HashMap<Point, u64>, point_at: &Point, point_next: &Point) -> bool {
+    let (at, next) = match (grid.get(point_at), grid.get(point_next)) {
+      (Some(at), Some(next)) => (*at, *next),
+      _ => return false,
+    };
+
+    if next >= at {
+      self.max_ascent.is_none_or(|max| next - at <= max)
+    } else {
+      self.max_descent.is_none_or(|max| at - next <= max)
+    }
+  }
+}
+
+// Cheapest route under `rules` as (total cost, every point visited from
+// `start` to `dest`), or `None` when the rules never let you reach `dest`
+pub fn least_cost_route(grid: &HashMap<Point, u64>, start: &Point, dest: &Point, rules: &ClimbRules) -> Option<(u64, Vec<Point>)> {
+  let neighbors = rules.neighbors();
+  let mut to_visit: BinaryHeap<Reverse<(u64, Point)>> = BinaryHeap::new();
+  let mut cost: HashMap<Point, u64> = HashMap::new();
+  let mut came_from: HashMap<Point, Point> = HashMap::new();
+
+  to_visit.push(Reverse((0, *start)));
+  cost.insert(*start, 0);
+
+  while let Some(Reverse((current_cost, current))) = to_visit.pop() {
+    if current == *dest {
+      let mut path = vec![current];
+      while let Some(previous) = came_from.get(path.last().unwrap()) {
+        path.push(*previous);
+      }
+      path.reverse();
+      return Some((current_cost, path));
+    }
+    if current_cost > cost[&current] {
+      continue;
+    }
+
+    for n in &neighbors {
+      let next = current + *n;
+      if !rules.move_allowed(grid, &current, &next) {
+        continue;
+      }
+
+      let next_cost = current_cost + rules.step_cost.cost(grid[&current], grid[&next]);
+      if cost.get(&next).is_none_or(|c| next_cost < *c) {
+        cost.insert(next, next_cost);
+        came_from.insert(next, current);
+        to_visit.push(Reverse((next_cost, next)));
+      }
+    }
   }
+
+  None
 }
 
 fn find_point_for_value(grid: &HashMap<Point, char>, target: char) -> Point {
@@ -134,7 +337,7 @@ pub fn lines_to_grid_char_val(lines: &Vec<String>) -> HashMap<Point, char> {
 #[cfg(test)]
 mod tests {
   use crate::day08::Point;
-  use crate::day12::{convert_grid_values_to_numeric, find_point_for_value, lines_to_grid_char_val, move_allowed, score_for_letter, solve_one, solve_two};
+  use crate::day12::{ClimbRules, convert_grid_values_to_numeric, convert_grid_values_with_rules, find_point_for_value, least_cost_route, lines_to_grid_char_val, move_allowed, render_path, score_for_letter, shortest_path, solve_one, solve_two, StepCost};
 
   #[test]
   fn test_scoring_for_grid() {
@@ -168,7 +371,7 @@ mod tests {
     let grid = convert_grid_values_to_numeric(&grid);
 
     let result = solve_one(&grid, &start, &dest);
-    assert_eq!(result, 31);
+    assert_eq!(result, Some(31));
   }
 
   #[test]
@@ -179,7 +382,92 @@ mod tests {
     let grid = convert_grid_values_to_numeric(&grid);
 
     let result = solve_two(&grid, &dest);
-    assert_eq!(result, 29);
+    assert_eq!(result, Some(29));
+  }
+
+  #[test]
+  fn test_shortest_path_and_render() {
+    let lines = get_input();
+    let char_grid = lines_to_grid_char_val(&lines);
+    let start = find_point_for_value(&char_grid, 'S');
+    let dest = find_point_for_value(&char_grid, 'E');
+    let grid = convert_grid_values_to_numeric(&char_grid);
+
+    let path = shortest_path(&grid, &start, &dest).unwrap();
+    assert_eq!(path.len(), 32);
+    assert_eq!(path.first(), Some(&start));
+    assert_eq!(path.last(), Some(&dest));
+    for step in path.windows(2) {
+      assert!(move_allowed(&grid, &step[0], &step[1]));
+    }
+
+    let rendered = render_path(&char_grid, &path);
+    assert_eq!(rendered.lines().count(), 5);
+    assert_eq!(rendered.chars().filter(|c| ['>', '<', '^', 'v'].contains(c)).count(), 31);
+    assert_eq!(rendered.chars().filter(|c| *c == 'E').count(), 1);
+  }
+
+  #[test]
+  fn test_unreachable_summit() {
+    let lines = vec!["Sbz".to_owned(), "azE".to_owned()];
+    let char_grid = lines_to_grid_char_val(&lines);
+    let start = find_point_for_value(&char_grid, 'S');
+    let dest = find_point_for_value(&char_grid, 'E');
+    let grid = convert_grid_values_to_numeric(&char_grid);
+
+    assert_eq!(solve_one(&grid, &start, &dest), None);
+    assert_eq!(solve_two(&grid, &dest), None);
+    assert_eq!(shortest_path(&grid, &start, &dest), None);
+  }
+
+  #[test]
+  fn test_least_cost_route_with_puzzle_rules() {
+    let lines = get_input();
+    let char_grid = lines_to_grid_char_val(&lines);
+    let start = find_point_for_value(&char_grid, 'S');
+    let dest = find_point_for_value(&char_grid, 'E');
+    let grid = convert_grid_values_to_numeric(&char_grid);
+
+    let (cost, path) = least_cost_route(&grid, &start, &dest, &ClimbRules::default()).unwrap();
+    assert_eq!(cost, 31);
+    assert_eq!(path.len(), 32);
+  }
+
+  #[test]
+  fn test_least_total_climb() {
+    let lines = get_input();
+    let char_grid = lines_to_grid_char_val(&lines);
+    let start = find_point_for_value(&char_grid, 'S');
+    let dest = find_point_for_value(&char_grid, 'E');
+    let rules = ClimbRules { step_cost: StepCost::total_climb(), ..ClimbRules::default() };
+    let grid = convert_grid_values_with_rules(&char_grid, &rules);
+
+    // Climbing from a to z can't be done with less than 25 levels gained
+    let (cost, _) = least_cost_route(&grid, &start, &dest, &rules).unwrap();
+    assert_eq!(cost, 25);
+  }
+
+  #[test]
+  fn test_bidirectional_and_diagonal_rules() {
+    let lines = vec!["Sbcd".to_owned(), "zzzd".to_owned(), "Ebcd".to_owned()];
+    let char_grid = lines_to_grid_char_val(&lines);
+    let start = find_point_for_value(&char_grid, 'S');
+    let dest = find_point_for_value(&char_grid, 'E');
+
+    // E is `a` here, so the walk has to come back down gently
+    let rules = ClimbRules { max_descent: Some(1), end_height: 1, ..ClimbRules::default() };
+    let grid = convert_grid_values_with_rules(&char_grid, &rules);
+    assert!(!rules.move_allowed(&grid, &Point { x: 0, y: 0 }, &Point { x: 0, y: 1 }));
+    let (cost, path) = least_cost_route(&grid, &start, &dest, &rules).unwrap();
+    assert_eq!(cost, 8);
+    assert_eq!(path.last(), Some(&dest));
+
+    let rules = ClimbRules { diagonal: true, ..rules };
+    let (cost, _) = least_cost_route(&grid, &start, &dest, &rules).unwrap();
+    assert_eq!(cost, 6);
+
+    let rules = ClimbRules { max_descent: Some(0), ..rules };
+    assert_eq!(least_cost_route(&grid, &start, &dest, &rules), None);
   }
 
   fn get_input() -> Vec<String> {
@@ -1,7 +1,7 @@
-use std::cmp::{max, Ordering};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-
-use serde_json::{Number, Value};
+use std::fmt;
+use std::str::FromStr;
 
 use crate::utils::read_chunks;
 
@@ -15,8 +15,7 @@ pub fn part_two() -> u64 {
 
   let packets: Vec<String> = chunks
     .iter()
-    .map(|c| c.split('\n'))
-    .flatten()
+    .flat_map(|c| c.split('\n'))
     .map(|s| s.to_string())
     .filter(|s| !s.is_empty())
     .collect();
@@ -24,211 +23,498 @@ pub fn part_two() -> u64 {
   solve_two(&packets)
 }
 
-fn solve_one(chunks: &Vec<String>) -> u64 {
+#[derive(Debug, Clone)]
+pub enum Packet {
+  Int(u64),
+  List(Vec<Packet>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketParseError {
+  pub position: usize,
+  pub message: String,
+}
+
+impl fmt::Display for PacketParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "invalid packet at position {}: {}", self.position, self.message)
+  }
+}
+
+impl FromStr for Packet {
+  type Err = PacketParseError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bytes = s.trim().as_bytes();
+    let mut position = 0;
+    let packet = parse_packet(bytes, &mut position)?;
+    if position != bytes.len() {
+      return Err(parse_error(position, "unexpected trailing characters"));
+    }
+    Ok(packet)
+  }
+}
+
+fn parse_error(position: usize, message: &str) -> PacketParseError {
+  PacketParseError { position, message: message.to_string() }
+}
+
+// Recursive descent over the raw bytes, `position` is left just past the
+// packet that was parsed
+fn parse_packet(bytes: &[u8], position: &mut usize) -> Result<Packet, PacketParseError> {
+  match bytes.get(*position) {
+    None => Err(parse_error(*position, "unexpected end of packet")),
+    Some(b'[') => {
+      *position += 1;
+      let mut items = vec![];
+      if bytes.get(*position) == Some(&b']') {
+        *position += 1;
+        return Ok(Packet::List(items));
+      }
+      loop {
+        items.push(parse_packet(bytes, position)?);
+        match bytes.get(*position) {
+          Some(b',') => *position += 1,
+          Some(b']') => {
+            *position += 1;
+            return Ok(Packet::List(items));
+          }
+          None => return Err(parse_error(*position, "unexpected end of packet, expected ',' or ']'")),
+          Some(_) => return Err(parse_error(*position, "expected ',' or ']'")),
+        }
+      }
+    }
+    Some(b) if b.is_ascii_digit() => {
+      let mut value: u64 = 0;
+      while let Some(digit) = bytes.get(*position).filter(|b| b.is_ascii_digit()) {
+        value = value
+          .checked_mul(10)
+          .and_then(|v| v.checked_add((digit - b'0') as u64))
+          .ok_or_else(|| parse_error(*position, "integer is too large"))?;
+        *position += 1;
+      }
+      Ok(Packet::Int(value))
+    }
+    Some(_) => Err(parse_error(*position, "expected '[' or a digit")),
+  }
+}
+
+impl fmt::Display for Packet {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Packet::Int(n) => write!(f, "{}", n),
+      Packet::List(items) => {
+        write!(f, "[")?;
+        for (i, item) in items.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+      }
+    }
+  }
+}
+
+// Ordering from the puzzle: integers compare by value, lists compare item by
+// item with the shorter list first, and an integer against a list is treated
+// as a list holding just that integer
+impl Ord for Packet {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (Packet::Int(left), Packet::Int(right)) => left.cmp(right),
+      (Packet::List(left), Packet::List(right)) => left.cmp(right),
+      (Packet::Int(left), Packet::List(right)) => [Packet::Int(*left)].as_slice().cmp(right.as_slice()),
+      (Packet::List(left), Packet::Int(right)) => left.as_slice().cmp([Packet::Int(*right)].as_slice()),
+    }
+  }
+}
+
+impl PartialOrd for Packet {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// Equality has to agree with the ordering, so `[[2]]` and `2` are equal here.
+// Compare the `Display` text when you need packets to be written identically
+impl PartialEq for Packet {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Packet {}
+
+fn solve_one(chunks: &[String]) -> u64 {
   let index_to_in_order: HashMap<usize, Ordering> = chunks
     .iter()
-    .map(|c| c.split("\n").collect::<Vec<&str>>())
+    .map(|c| c.split('\n').collect::<Vec<&str>>())
     .enumerate()
     .map(|p| {
-      (
-        p.0,
-        compare(
-          &serde_json::from_str(p.1[0]).unwrap(),
-          &serde_json::from_str(p.1[1]).unwrap(),
-        )
-      )
+      let left: Packet = p.1[0].parse().unwrap();
+      let right: Packet = p.1[1].parse().unwrap();
+      (p.0, left.cmp(&right))
     })
     .collect();
 
   let sum: usize = index_to_in_order
     .iter()
-    .filter(|p| (*p).1 == &Ordering::Less)
+    .filter(|p| p.1 == &Ordering::Less)
     .map(|p| *p.0 + 1)// Indexes are 1-based!!
     .sum();
 
   sum as u64
 }
 
-fn solve_two(packets: &Vec<String>) -> u64 {
-  let divider_packets = vec!["[[2]]".to_string(), "[[6]]".to_string()];
-  let mut packets = packets.clone();
-  packets.extend(divider_packets.clone());
-  let mut parsed_packets: Vec<Value> = packets
-    .iter()
-    .map(|p| serde_json::from_str(p).unwrap())
-    .collect();
+fn solve_two(packets: &[String]) -> u64 {
+  let packets: Vec<Packet> = packets.iter().map(|p| p.parse().unwrap()).collect();
+  let divider_packets: Vec<Packet> = ["[[2]]", "[[6]]"].iter().map(|p| p.parse().unwrap()).collect();
 
-  parsed_packets.sort_by(compare);
+  divider_indices(&packets, &divider_packets)
+    .iter()
+    .product::<usize>() as u64
+}
 
-  let product: usize = parsed_packets
+// Every non-empty line is a packet, errors point at the (1-based) line
+pub fn parse_packet_lines(lines: &[String]) -> Result<Vec<Packet>, String> {
+  lines
     .iter()
-    .map(|v| serde_json::to_string(v).unwrap())
     .enumerate()
-    .filter(|p| divider_packets.contains(&(*p).1.to_owned()))
-    .map(|p| p.0 + 1)
-    .product();
-
-  product as u64
+    .filter(|(_, l)| !l.trim().is_empty())
+    .map(|(i, l)| l.parse::<Packet>().map_err(|e| format!("line {}: {}", i + 1, e)))
+    .collect()
 }
 
-fn compare(left: &Value, right: &Value) -> Ordering {
-  match left {
-    Value::Number(n) => { return handle_left_number(n, right); }
-    Value::Array(a) => { return handle_left_array(a, right); }
-    _ => panic!("Unexpected value type")
-  }
+pub fn sort_packets(packets: &[Packet]) -> Vec<Packet> {
+  let mut sorted = packets.to_vec();
+  sorted.sort();
+  sorted
 }
 
-fn handle_left_array(left_array: &Vec<Value>, right: &Value) -> Ordering {
-  match right {
-    Value::Number(_) => { handle_two_array(left_array, &vec![right.to_owned()]) }
-    Value::Array(a) => { handle_two_array(left_array, a) }
-    _ => panic!("Unexpected value type")
+// Packets that are written identically more than once, with the 1-based
+// positions of each copy, in the order they first appear
+pub fn find_duplicates(packets: &[Packet]) -> Vec<(String, Vec<usize>)> {
+  let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+  let mut first_seen: Vec<String> = vec![];
+  for (i, packet) in packets.iter().enumerate() {
+    let text = packet.to_string();
+    let entry = positions.entry(text.clone()).or_default();
+    if entry.is_empty() {
+      first_seen.push(text);
+    }
+    entry.push(i + 1);
   }
+
+  first_seen
+    .into_iter()
+    .map(|text| {
+      let found = positions.remove(&text).unwrap();
+      (text, found)
+    })
+    .filter(|(_, found)| found.len() > 1)
+    .collect()
 }
 
-fn handle_two_array(left_array: &Vec<Value>, right_array: &Vec<Value>) -> Ordering {
-  let max_len = max(left_array.len(), right_array.len());
+// 1-based index each divider would end up at if it was added after the
+// packets and everything stable sorted. Counting what sorts before each
+// divider is enough, there's no need to actually sort
+pub fn divider_indices(packets: &[Packet], dividers: &[Packet]) -> Vec<usize> {
+  dividers
+    .iter()
+    .enumerate()
+    .map(|(i, divider)| {
+      // Packets equal to a divider were added first, so stay ahead of it, and
+      // equal dividers keep the order they were given in
+      let packets_before = packets.iter().filter(|p| *p <= divider).count();
+      let dividers_before = dividers
+        .iter()
+        .enumerate()
+        .filter(|(j, other)| *other < divider || (*other == divider && *j < i))
+        .count();
+      packets_before + dividers_before + 1
+    })
+    .collect()
+}
 
-  for i in 0..max_len {
-    let next_left = left_array.get(i);
-    let next_right = right_array.get(i);
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderExplanation {
+  pub ordering: Ordering,
+  // Indexes into the nested lists to get to where the packets first differ.
+  // An integer compared against a list counts as a list of one item
+  pub path: Vec<usize>,
+  pub reason: String,
+}
 
-    if next_left.is_none() && next_right.is_none() {
-      return Ordering::Equal;
-    } else if next_left.is_none() && next_right.is_some() {
-      return Ordering::Less;
-    } else if next_left.is_some() && next_right.is_none() {
-      return Ordering::Greater;
+impl fmt::Display for OrderExplanation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let verdict = match self.ordering {
+      Ordering::Less => "in the right order",
+      Ordering::Equal => "equal",
+      Ordering::Greater => "not in the right order",
+    };
+    let path: String = self.path.iter().map(|i| format!("[{}]", i)).collect();
+    if self.ordering == Ordering::Equal {
+      write!(f, "Packets are {}", verdict)
     } else {
-      let left = next_left.unwrap();
-      let right = next_right.unwrap();
-
-      let result = match left {
-        Value::Number(n) => { handle_left_number(n, right) }
-        Value::Array(a) => { handle_left_array(a, right) }
-        _ => panic!("Unexpected value type")
-      };
-
-      if result != Ordering::Equal {
-        return result;
-      }
+      write!(f, "Packets are {}: at {} {}", verdict, if path.is_empty() { "top level".to_string() } else { path }, self.reason)
     }
   }
+}
+
+// Walks both packets the same way `Ord` does, but remembers where the first
+// difference was found and why it decided the order
+pub fn explain_order(left: &Packet, right: &Packet) -> OrderExplanation {
+  let mut path = vec![];
+  match find_difference(left, right, &mut path) {
+    Some((ordering, reason)) => OrderExplanation { ordering, path, reason },
+    None => OrderExplanation { ordering: Ordering::Equal, path: vec![], reason: "packets are equal".to_string() },
+  }
+}
 
-  return Ordering::Equal;
+fn find_difference(left: &Packet, right: &Packet, path: &mut Vec<usize>) -> Option<(Ordering, String)> {
+  match (left, right) {
+    (Packet::Int(l), Packet::Int(r)) => match l.cmp(r) {
+      Ordering::Equal => None,
+      Ordering::Less => Some((Ordering::Less, format!("left {} is smaller than right {}", l, r))),
+      Ordering::Greater => Some((Ordering::Greater, format!("left {} is larger than right {}", l, r))),
+    },
+    (Packet::List(l), Packet::List(r)) => find_list_difference(l, r, path),
+    (Packet::Int(l), Packet::List(r)) => find_list_difference(&[Packet::Int(*l)], r, path),
+    (Packet::List(l), Packet::Int(r)) => find_list_difference(l, &[Packet::Int(*r)], path),
+  }
 }
 
-fn handle_left_number(left_number: &Number, right: &Value) -> Ordering {
-  match right {
-    Value::Number(n) => {
-      if left_number.as_i64() < n.as_i64() {
-        Ordering::Less
-      } else if left_number.as_i64() > n.as_i64() {
-        Ordering::Greater
-      } else {
-        Ordering::Equal
+fn find_list_difference(left: &[Packet], right: &[Packet], path: &mut Vec<usize>) -> Option<(Ordering, String)> {
+  for i in 0..left.len().max(right.len()) {
+    match (left.get(i), right.get(i)) {
+      (None, Some(_)) => {
+        return Some((Ordering::Less, format!("left list ran out of items first ({} vs {})", left.len(), right.len())));
+      }
+      (Some(_), None) => {
+        return Some((Ordering::Greater, format!("right list ran out of items first ({} vs {})", left.len(), right.len())));
       }
+      (Some(l), Some(r)) => {
+        path.push(i);
+        let difference = find_difference(l, r, path);
+        if difference.is_some() {
+          return difference;
+        }
+        path.pop();
+      }
+      (None, None) => unreachable!(),
     }
-    Value::Array(a) => { handle_two_array(&vec![Value::Number(left_number.to_owned())], a) }
-    _ => panic!("Unexpected value type")
   }
+
+  None
 }
 
 #[cfg(test)]
 mod tests {
   use std::cmp::Ordering;
 
-  use crate::day13::{compare, solve_one, solve_two};
+  use crate::day13::{divider_indices, explain_order, find_duplicates, Packet, parse_packet_lines, solve_one, solve_two, sort_packets};
 
   #[test]
   fn test_pair_1() {
-    let left = serde_json::from_str("[1,1,3,1,1]").unwrap();
-    let right = serde_json::from_str("[1,1,5,1,1]").unwrap();
+    let left: Packet = "[1,1,3,1,1]".parse().unwrap();
+    let right: Packet = "[1,1,5,1,1]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Less
     );
   }
 
   #[test]
   fn test_pair_2() {
-    let left = serde_json::from_str("[[1],[2,3,4]]").unwrap();
-    let right = serde_json::from_str("[[1],4]").unwrap();
+    let left: Packet = "[[1],[2,3,4]]".parse().unwrap();
+    let right: Packet = "[[1],4]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Less
     );
   }
 
   #[test]
   fn test_pair_3() {
-    let left = serde_json::from_str("[9]").unwrap();
-    let right = serde_json::from_str("[[8,7,6]]").unwrap();
+    let left: Packet = "[9]".parse().unwrap();
+    let right: Packet = "[[8,7,6]]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Greater
     );
   }
 
   #[test]
   fn test_pair_4() {
-    let left = serde_json::from_str("[[4,4],4,4]").unwrap();
-    let right = serde_json::from_str("[[4,4],4,4,4]").unwrap();
+    let left: Packet = "[[4,4],4,4]".parse().unwrap();
+    let right: Packet = "[[4,4],4,4,4]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Less
     );
   }
 
   #[test]
   fn test_pair_5() {
-    let left = serde_json::from_str("[7,7,7,7]").unwrap();
-    let right = serde_json::from_str("[7,7,7]").unwrap();
+    let left: Packet = "[7,7,7,7]".parse().unwrap();
+    let right: Packet = "[7,7,7]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Greater
     );
   }
 
   #[test]
   fn test_pair_6() {
-    let left = serde_json::from_str("[]").unwrap();
-    let right = serde_json::from_str("[3]").unwrap();
+    let left: Packet = "[]".parse().unwrap();
+    let right: Packet = "[3]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Less
     );
   }
 
   #[test]
   fn test_pair_7() {
-    let left = serde_json::from_str("[[[]]]").unwrap();
-    let right = serde_json::from_str("[[]]").unwrap();
+    let left: Packet = "[[[]]]".parse().unwrap();
+    let right: Packet = "[[]]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Greater
     );
   }
 
   #[test]
   fn test_pair_8() {
-    let left = serde_json::from_str("[1,[2,[3,[4,[5,6,7]]]],8,9]").unwrap();
-    let right = serde_json::from_str("[1,[2,[3,[4,[5,6,0]]]],8,9]").unwrap();
+    let left: Packet = "[1,[2,[3,[4,[5,6,7]]]],8,9]".parse().unwrap();
+    let right: Packet = "[1,[2,[3,[4,[5,6,0]]]],8,9]".parse().unwrap();
 
     assert_eq!(
-      compare(&left, &right),
+      left.cmp(&right),
       Ordering::Greater
     );
   }
 
+  #[test]
+  fn test_display_round_trips() {
+    let text = "[1,[2,[3,[4,[5,6,7]]]],8,9]";
+    let packet: Packet = text.parse().unwrap();
+    assert_eq!(packet.to_string(), text);
+
+    let packet: Packet = "  []\t".parse().unwrap();
+    assert_eq!(packet.to_string(), "[]");
+  }
+
+  #[test]
+  fn test_mixed_types_are_equal() {
+    let left: Packet = "[[2]]".parse().unwrap();
+    let right: Packet = "2".parse().unwrap();
+    assert_eq!(left, right);
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    assert_eq!("".parse::<Packet>().unwrap_err().position, 0);
+    assert_eq!("[1,2".parse::<Packet>().unwrap_err().position, 4);
+    assert_eq!("[1,,2]".parse::<Packet>().unwrap_err().position, 3);
+    assert_eq!("[1]]".parse::<Packet>().unwrap_err().position, 3);
+    assert_eq!("[a]".parse::<Packet>().unwrap_err().position, 1);
+    assert_eq!("[1 2]".parse::<Packet>().unwrap_err().position, 2);
+    assert_eq!("[99999999999999999999]".parse::<Packet>().unwrap_err().message, "integer is too large");
+    assert_eq!(
+      "[1,2".parse::<Packet>().unwrap_err().to_string(),
+      "invalid packet at position 4: unexpected end of packet, expected ',' or ']'"
+    );
+  }
+
+  #[test]
+  fn test_divider_indices_match_sorting() {
+    let packets = parse_packet_lines(&get_input_all_individual_packets()).unwrap();
+    let dividers = parse_packet_lines(&["[[6]]".to_string(), "[[2]]".to_string(), "[]".to_string()]).unwrap();
+    // The sample has a [] packet, which the [] divider sorts after
+    assert_eq!(divider_indices(&packets, &dividers), vec![15, 11, 2]);
+
+    let mut everything = packets.clone();
+    everything.extend(dividers.clone());
+    let sorted: Vec<String> = sort_packets(&everything).iter().map(|p| p.to_string()).collect();
+    assert_eq!(sorted[0..2], ["[]", "[]"]);
+    assert_eq!(sorted[10], "[[2]]");
+    assert_eq!(sorted[14], "[[6]]");
+  }
+
+  #[test]
+  fn test_divider_after_equal_packet() {
+    // [2] compares equal to [[2]], and a stable sort keeps it first
+    let packets = parse_packet_lines(&["[1]".to_string(), "[2]".to_string(), "[3]".to_string()]).unwrap();
+    let dividers = parse_packet_lines(&["[[2]]".to_string(), "[[2]]".to_string()]).unwrap();
+    assert_eq!(divider_indices(&packets, &dividers), vec![3, 4]);
+
+    let mut everything = packets.clone();
+    everything.extend(dividers.clone());
+    let sorted: Vec<String> = sort_packets(&everything).iter().map(|p| p.to_string()).collect();
+    assert_eq!(sorted, vec!["[1]", "[2]", "[[2]]", "[[2]]", "[3]"]);
+  }
+
+  #[test]
+  fn test_sorted_sample() {
+    let packets = parse_packet_lines(&get_input_all_individual_packets()).unwrap();
+    let sorted: Vec<String> = sort_packets(&packets).iter().map(|p| p.to_string()).collect();
+    assert_eq!(sorted.first().unwrap(), "[]");
+    assert_eq!(sorted.last().unwrap(), "[9]");
+    assert_eq!(sorted.len(), 16);
+  }
+
+  #[test]
+  fn test_find_duplicates() {
+    let lines: Vec<String> = ["[1,2]", "[3]", "[1,2]", "[[3]]", "[3]", "[1,2]"].iter().map(|l| l.to_string()).collect();
+    let packets = parse_packet_lines(&lines).unwrap();
+    assert_eq!(find_duplicates(&packets), vec![
+      ("[1,2]".to_string(), vec![1, 3, 6]),
+      ("[3]".to_string(), vec![2, 5]),
+    ]);
+  }
+
+  #[test]
+  fn test_parse_packet_lines_reports_line() {
+    let lines: Vec<String> = ["[1]", "", "[1,]"].iter().map(|l| l.to_string()).collect();
+    assert_eq!(parse_packet_lines(&lines).unwrap_err(), "line 3: invalid packet at position 3: expected '[' or a digit");
+  }
+
+  #[test]
+  fn test_explain_order() {
+    let left: Packet = "[1,[2,[3,[4,[5,6,7]]]],8,9]".parse().unwrap();
+    let right: Packet = "[1,[2,[3,[4,[5,6,0]]]],8,9]".parse().unwrap();
+    let explanation = explain_order(&left, &right);
+    assert_eq!(explanation.ordering, Ordering::Greater);
+    assert_eq!(explanation.path, vec![1, 1, 1, 1, 2]);
+    assert_eq!(explanation.to_string(), "Packets are not in the right order: at [1][1][1][1][2] left 7 is larger than right 0");
+
+    let left: Packet = "[[4,4],4,4]".parse().unwrap();
+    let right: Packet = "[[4,4],4,4,4]".parse().unwrap();
+    let explanation = explain_order(&left, &right);
+    assert_eq!(explanation.ordering, Ordering::Less);
+    assert!(explanation.path.is_empty());
+    assert_eq!(explanation.reason, "left list ran out of items first (3 vs 4)");
+
+    let left: Packet = "[9]".parse().unwrap();
+    let right: Packet = "[[8,7,6]]".parse().unwrap();
+    let explanation = explain_order(&left, &right);
+    assert_eq!(explanation.path, vec![0, 0]);
+    assert_eq!(explanation.ordering, left.cmp(&right));
+
+    let explanation = explain_order(&left, &left);
+    assert_eq!(explanation.ordering, Ordering::Equal);
+  }
+
   #[test]
   fn test_example_1() {
     let chunks = get_input_as_chunks();
@@ -277,8 +563,7 @@ mod tests {
   fn get_input_all_individual_packets() -> Vec<String> {
     get_input_as_chunks()
       .iter()
-      .map(|c| c.split('\n'))
-      .flatten()
+      .flat_map(|c| c.split('\n'))
       .map(|s| s.to_string())
       .collect()
   }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use crate::day08::Point;
+use crate::day14_scan::{parse_scan, paths_to_grid};
 
 use crate::utils::read_chunks;
 
@@ -18,153 +19,285 @@ pub fn part_two() -> u64 {
   simulate_with_infinite_floor(&grid)
 }
 
-fn parse_input(lines: &Vec<String>) -> HashMap<Point, char> {
-  let mut grid = HashMap::new();
-
-  lines
-    .iter()
-    .map(|l| l.split(" -> "))
-    .map(|parts| parts.map(|p| p.to_string()).collect::<Vec<String>>())
-    .map(|parts| points_to_windows_pairs(&parts))
-    .flatten()
-    .for_each(|point_pair| add_to_grid(&point_pair, &mut grid));
-
-  grid
+// Simulation over the puzzle input's rock scan
+pub fn sand_simulation(sources: Vec<SandSource>, floor_policy: FloorPolicy) -> SandSimulation {
+  let lines = read_chunks("day14.txt", "\n");
+  let grid = parse_input(&lines);
+  SandSimulation::new(&grid, sources, floor_policy)
 }
 
-fn add_to_grid(point_pair: &(Point, Point), grid: &mut HashMap<Point, char>) {
-  let (p1, p2) = point_pair;
-  if p1.x == p2.x {
-    if p1.y < p2.y {
-      for y in p1.y..=p2.y {
-        grid.insert(Point { x: p1.x, y: y }, '#');
-      }
-    } else {
-      for y in p2.y..=p1.y {
-        grid.insert(Point { x: p1.x, y: y }, '#');
-      }
-    }
-  } else {
-    if p1.x < p2.x {
-      for x in p1.x..=p2.x {
-        grid.insert(Point { x: x, y: p1.y }, '#');
-      }
-    } else {
-      for x in p2.x..=p1.x {
-        grid.insert(Point { x: x, y: p1.y }, '#');
-      }
+fn parse_input(lines: &[String]) -> HashMap<Point, char> {
+  match parse_scan(lines) {
+    Ok(paths) => paths_to_grid(&paths),
+    Err(errors) => {
+      let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+      panic!("Invalid cave scan:\n{}", errors.join("\n"))
     }
   }
 }
 
-fn points_to_windows_pairs(parts: &Vec<String>) -> Vec<(Point, Point)> {
-  parts
-    .windows(2)
-    .map(|w| {
-      (
-        str_to_point(w.get(0).unwrap()),
-        str_to_point(w.get(1).unwrap())
-      )
-    })
-    .collect()
+fn simulate_with_abyss(grid: &HashMap<Point, char>) -> u64 {
+  let mut simulation = SandSimulation::new(grid, vec![SandSource::puzzle()], FloorPolicy::Abyss);
+  simulation.run(None)
+}
+
+fn simulate_with_infinite_floor(grid: &HashMap<Point, char>) -> u64 {
+  let mut simulation = SandSimulation::new(grid, vec![SandSource::puzzle()], FloorPolicy::Floor { depth: 2 });
+  simulation.run(None)
+}
+
+const EMPTY: char = '.';
+
+// What happens once grains go below the lowest rock in the scan
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloorPolicy {
+  // Anything falling past the lowest rock is gone, and so is every grain
+  // after it from the same source
+  Abyss,
+  // Solid floor `depth` rows below the lowest rock, stretching forever sideways
+  Floor { depth: i64 },
+}
+
+// A grain tries each move in order and settles when none of them are free.
+// Every move has to go down at least one row so grains always stop
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+  pub symbol: char,
+  pub moves: Vec<Point>,
 }
 
-fn str_to_point(str_point: &String) -> Point {
-  let parts = str_point.split(",").collect::<Vec<&str>>();
-  Point {
-    x: parts.get(0).unwrap().parse().unwrap(),
-    y: parts.get(1).unwrap().parse().unwrap(),
+impl Material {
+  pub fn sand() -> Material {
+    Material {
+      symbol: 'o',
+      moves: vec![Point { x: 0, y: 1 }, Point { x: -1, y: 1 }, Point { x: 1, y: 1 }],
+    }
   }
 }
 
-fn simulate_with_abyss(grid: &HashMap<Point, char>) -> u64 {
-  let mut grid = grid.clone();
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandSource {
+  pub position: Point,
+  pub material: Material,
+}
 
-  while add_sand_grain(&mut grid) {
-    // Just want to add sand grains, nothing to do in here
+impl SandSource {
+  pub fn puzzle() -> SandSource {
+    SandSource { position: Point { x: 500, y: 0 }, material: Material::sand() }
   }
+}
 
-  let sand_count: usize = grid
-    .values()
-    .filter(|v| **v == 'o')
-    .count();
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GrainOutcome {
+  Settled(Point),
+  FellIntoAbyss,
+  SourceBlocked,
+}
 
-  // print_grid(&grid);
-  sand_count as u64
+// `path` is every position the grain passed through, from the source to where
+// it settled (or the last point before it fell). Only filled in when path
+// tracking is turned on
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grain {
+  pub source: usize,
+  pub outcome: GrainOutcome,
+  pub path: Vec<Point>,
 }
 
-fn simulate_with_infinite_floor(grid: &HashMap<Point, char>) -> u64 {
-  let mut grid = grid.clone();
-  let floor_y = grid.keys().map(|k| k.y).max().unwrap() + 2;
+// Dense grid big enough for every grain that can possibly settle. Each source
+// keeps the path of its last grain; the next grain follows exactly the same
+// path until it hits the spot that just filled up, so we pick up from there
+// instead of falling all the way from the source again
+pub struct SandSimulation {
+  grid: Vec<char>,
+  min_x: i64,
+  min_y: i64,
+  width: i64,
+  // Lowest row a grain can be in, rows past this are abyss or floor
+  max_y: i64,
+  floor_policy: FloorPolicy,
+  sources: Vec<SandSource>,
+  paths: Vec<Vec<Point>>,
+  finished: Vec<bool>,
+  settled: u64,
+  track_paths: bool,
+}
 
-  while add_sand_grain(&mut grid) {
-    // Update the floor
-    let min_x = grid.keys().filter(|p| p.y != floor_y).map(|k| k.x).min().unwrap() -5;
-    let max_x = grid.keys().filter(|p| p.y != floor_y).map(|k| k.x).max().unwrap() + 5;
+impl SandSimulation {
+  pub fn new(rock: &HashMap<Point, char>, sources: Vec<SandSource>, floor_policy: FloorPolicy) -> SandSimulation {
+    for source in &sources {
+      if source.material.moves.iter().any(|m| m.y < 1) {
+        panic!("Every move for {} has to go down at least one row", source.material.symbol);
+      }
+    }
+    if let FloorPolicy::Floor { depth } = floor_policy {
+      if depth < 1 {
+        panic!("The floor has to be at least one row below the lowest rock, not {}", depth);
+      }
+    }
 
-    for x in min_x..=max_x {
-      grid.insert(Point { x, y: floor_y }, '#');
+    let lowest_rock = rock.keys().map(|p| p.y).max().unwrap_or(0);
+    let max_y = match floor_policy {
+      FloorPolicy::Abyss => lowest_rock,
+      FloorPolicy::Floor { depth } => lowest_rock + depth - 1,
+    };
+    let min_y = rock.keys().chain(sources.iter().map(|s| &s.position)).map(|p| p.y).min().unwrap_or(0);
+
+    // Grains can only drift sideways a little with every row they fall, so
+    // this is as wide as a pile can ever get
+    let widest_move = sources
+      .iter()
+      .flat_map(|s| s.material.moves.iter().map(|m| m.x.abs()))
+      .max()
+      .unwrap_or(0);
+    let margin = (max_y - min_y + 1) * widest_move + 1;
+    let min_x = rock.keys().chain(sources.iter().map(|s| &s.position)).map(|p| p.x).min().unwrap_or(0) - margin;
+    let max_x = rock.keys().chain(sources.iter().map(|s| &s.position)).map(|p| p.x).max().unwrap_or(0) + margin;
+    let width = max_x - min_x + 1;
+
+    let mut simulation = SandSimulation {
+      grid: vec![EMPTY; (width * (max_y - min_y + 1).max(0)) as usize],
+      min_x,
+      min_y,
+      width,
+      max_y,
+      floor_policy,
+      paths: vec![vec![]; sources.len()],
+      finished: vec![false; sources.len()],
+      sources,
+      settled: 0,
+      track_paths: false,
+    };
+    for (p, c) in rock {
+      if simulation.in_bounds(p) {
+        simulation.set(p, *c);
+      }
     }
+
+    simulation
+  }
+
+  pub fn with_path_tracking(mut self) -> SandSimulation {
+    self.track_paths = true;
+    self
   }
 
-  let sand_count: usize = grid
-    .values()
-    .filter(|v| **v == 'o')
-    .count();
+  fn in_bounds(&self, p: &Point) -> bool {
+    p.x >= self.min_x && p.x < self.min_x + self.width && p.y >= self.min_y && p.y <= self.max_y
+  }
 
-  // print_grid(&grid);
-  sand_count as u64
-}
+  fn index(&self, p: &Point) -> usize {
+    ((p.y - self.min_y) * self.width + (p.x - self.min_x)) as usize
+  }
 
-fn add_sand_grain(grid: &mut HashMap<Point, char>) -> bool {
-  let max_y = grid.keys().map(|k| k.y).max().unwrap() + 1;
-  let down = Point { x: 0, y: 1 };
-  let left = Point { x: -1, y: 1 };
-  let right = Point { x: 1, y: 1 };
-  let mut position = Point { x: 500, y: 0 };
-
-  loop {
-    // Failsafe in case we fall off the bottom
-    if position.y > max_y {
-      return false;
+  fn set(&mut self, p: &Point, c: char) {
+    let index = self.index(p);
+    self.grid[index] = c;
+  }
+
+  pub fn get(&self, p: &Point) -> char {
+    if self.in_bounds(p) {
+      self.grid[self.index(p)]
+    } else {
+      match self.floor_policy {
+        FloorPolicy::Floor { .. } if p.y == self.max_y + 1 => '#',
+        _ => EMPTY,
+      }
     }
+  }
+
+  fn is_free(&self, p: &Point) -> bool {
+    self.get(p) == EMPTY
+  }
+
+  pub fn settled_count(&self) -> u64 {
+    self.settled
+  }
+
+  pub fn is_finished(&self, source: usize) -> bool {
+    self.finished[source]
+  }
+
+  // Drops one grain from the given source and lets it come to rest
+  pub fn drop_grain(&mut self, source: usize) -> Grain {
+    let origin = self.sources[source].position;
+    let mut path = std::mem::take(&mut self.paths[source]);
 
-    // Failsafe for once we're blocked at the entrance
-    if *grid.get(&position).unwrap_or(&'.') == 'o' {
-      return false;
+    // With a single source only the end of the path can have filled up, but
+    // other sources can drop grains anywhere along it
+    if self.sources.len() == 1 {
+      while path.last().is_some_and(|p| !self.is_free(p)) {
+        path.pop();
+      }
+    } else if let Some(blocked_at) = path.iter().position(|p| !self.is_free(p)) {
+      path.truncate(blocked_at);
     }
 
-    let next_down_pos = position + down;
-    let next_down_val = grid.get(&next_down_pos).unwrap_or(&'.');
-    if *next_down_val == '.' {
-      position = next_down_pos;
-      continue;
+    if self.finished[source] || (path.is_empty() && !self.is_free(&origin)) {
+      self.finished[source] = true;
+      return Grain { source, outcome: GrainOutcome::SourceBlocked, path: vec![] };
+    }
+    if path.is_empty() {
+      path.push(origin);
     }
 
-    let next_left_pos = position + left;
-    let next_left_val = grid.get(&next_left_pos).unwrap_or(&'.');
-    if *next_left_val == '.' {
-      position = next_left_pos;
-      continue;
+    let material = &self.sources[source].material;
+    loop {
+      let position = *path.last().unwrap();
+      let next = material.moves.iter().map(|m| position + *m).find(|p| self.is_free(p));
+      match next {
+        Some(next) if next.y > self.max_y => {
+          // Only way past the bottom row without a floor
+          self.finished[source] = true;
+          let path = if self.track_paths { path } else { vec![] };
+          return Grain { source, outcome: GrainOutcome::FellIntoAbyss, path };
+        }
+        Some(next) => path.push(next),
+        None => {
+          let symbol = material.symbol;
+          self.set(&position, symbol);
+          self.settled += 1;
+          let grain_path = if self.track_paths { path.clone() } else { vec![] };
+          path.pop();
+          self.paths[source] = path;
+          return Grain { source, outcome: GrainOutcome::Settled(position), path: grain_path };
+        }
+      }
     }
+  }
 
-    let next_right_pos = position + right;
-    let next_right_val = grid.get(&next_right_pos).unwrap_or(&'.');
-    if *next_right_val == '.' {
-      position = next_right_pos;
-      continue;
+  // Drops grains from each source in turn until they're all blocked or have
+  // lost a grain to the abyss, or `max_grains` have settled. Returns the
+  // total number of settled grains
+  pub fn run(&mut self, max_grains: Option<u64>) -> u64 {
+    while self.finished.iter().any(|f| !f) {
+      for source in 0..self.sources.len() {
+        if max_grains.is_some_and(|max| self.settled >= max) {
+          return self.settled;
+        }
+        if !self.finished[source] {
+          self.drop_grain(source);
+        }
+      }
     }
 
-    // If we can't move anywhere, then we've settled at this position
-    grid.insert(position, 'o');
-    break;
+    self.settled
   }
 
-  true
+  // Rock and settled grains, without the floor
+  pub fn to_grid(&self) -> HashMap<Point, char> {
+    let mut grid = HashMap::new();
+    for (i, c) in self.grid.iter().enumerate() {
+      if *c != EMPTY {
+        let i = i as i64;
+        grid.insert(Point { x: self.min_x + i % self.width, y: self.min_y + i / self.width }, *c);
+      }
+    }
+    grid
+  }
 }
 
-#[allow(dead_code)]
-fn print_grid(grid: &HashMap<Point, char>) {
+pub fn print_grid(grid: &HashMap<Point, char>) {
   let min_x = grid.keys().map(|k| k.x).min().unwrap() - 1;
   let max_x = grid.keys().map(|k| k.x).max().unwrap() + 1;
   let min_y = grid.keys().map(|k| k.y).min().unwrap() - 1;
@@ -181,7 +314,10 @@ fn print_grid(grid: &HashMap<Point, char>) {
 
 #[cfg(test)]
 mod tests {
-  use crate::day14::{parse_input, print_grid, simulate_with_abyss, simulate_with_infinite_floor};
+  use std::collections::HashMap;
+
+  use crate::day08::Point;
+  use crate::day14::{FloorPolicy, GrainOutcome, Material, parse_input, print_grid, SandSimulation, SandSource, simulate_with_abyss, simulate_with_infinite_floor};
 
   #[test]
   fn test_part_1() {
@@ -199,6 +335,67 @@ mod tests {
     assert_eq!(simulate_with_infinite_floor(&grid), 93);
   }
 
+  #[test]
+  fn test_path_tracking() {
+    let input = get_input();
+    let grid = parse_input(&input);
+    let mut simulation = SandSimulation::new(&grid, vec![SandSource::puzzle()], FloorPolicy::Abyss).with_path_tracking();
+
+    let first = simulation.drop_grain(0);
+    assert_eq!(first.outcome, GrainOutcome::Settled(Point { x: 500, y: 8 }));
+    assert_eq!(first.path.len(), 9);
+    assert_eq!(first.path[0], Point { x: 500, y: 0 });
+
+    let second = simulation.drop_grain(0);
+    assert_eq!(second.outcome, GrainOutcome::Settled(Point { x: 499, y: 8 }));
+    assert_eq!(second.path.last(), Some(&Point { x: 499, y: 8 }));
+
+    simulation.run(None);
+    assert_eq!(simulation.settled_count(), 24);
+    assert_eq!(simulation.drop_grain(0).outcome, GrainOutcome::SourceBlocked);
+  }
+
+  #[test]
+  fn test_multiple_sources_and_materials() {
+    let mut grid = HashMap::new();
+    for x in 0..=10 {
+      grid.insert(Point { x, y: 5 }, '#');
+    }
+    let water = Material { symbol: '~', moves: vec![Point { x: 0, y: 1 }, Point { x: 1, y: 1 }, Point { x: -1, y: 1 }] };
+    let sources = vec![
+      SandSource { position: Point { x: 3, y: 0 }, material: Material::sand() },
+      SandSource { position: Point { x: 7, y: 0 }, material: water },
+    ];
+    let mut simulation = SandSimulation::new(&grid, sources, FloorPolicy::Abyss);
+    simulation.run(Some(8));
+    assert_eq!(simulation.settled_count(), 8);
+
+    let result = simulation.to_grid();
+    assert_eq!(result.values().filter(|c| **c == 'o').count(), 4);
+    assert_eq!(result.values().filter(|c| **c == '~').count(), 4);
+    assert_eq!(result.get(&Point { x: 3, y: 4 }), Some(&'o'));
+    assert_eq!(result.get(&Point { x: 7, y: 4 }), Some(&'~'));
+
+    // Both piles eventually spill over the edges of the ledge
+    simulation.run(None);
+    assert!(simulation.is_finished(0) && simulation.is_finished(1));
+  }
+
+  #[test]
+  fn test_floor_with_large_pile() {
+    let grid = HashMap::from([(Point { x: 500, y: 200 }, '#')]);
+    let mut simulation = SandSimulation::new(&grid, vec![SandSource::puzzle()], FloorPolicy::Floor { depth: 2 });
+    // Without any rock in the way the pile is a full triangle down to the floor
+    assert_eq!(simulation.run(None), 202 * 202 - 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "The floor has to be at least one row below the lowest rock, not 0")]
+  fn test_floor_at_rock_level() {
+    let grid = parse_input(&get_input());
+    SandSimulation::new(&grid, vec![SandSource::puzzle()], FloorPolicy::Floor { depth: 0 });
+  }
+
   fn get_input() -> Vec<String> {
     vec![
       "498,4 -> 498,6 -> 496,6".to_owned(),
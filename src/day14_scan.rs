@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::day08::Point;
+
+// Problem with one line of a cave scan, `line` is 1-based
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanError {
+  pub line: usize,
+  pub message: String,
+}
+
+impl fmt::Display for ScanError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+// Parses `x,y -> x,y -> ...` rock paths, checking every segment is a
+// horizontal or vertical line of at least one step. Collects every problem in
+// the scan rather than stopping at the first one
+pub fn parse_scan(lines: &[String]) -> Result<Vec<Vec<Point>>, Vec<ScanError>> {
+  let mut paths = vec![];
+  let mut errors = vec![];
+
+  for (i, line) in lines.iter().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let error = |message: String| ScanError { line: i + 1, message };
+
+    let points: Result<Vec<Point>, String> = line.split("->").map(|p| parse_point(p.trim())).collect();
+    let points = match points {
+      Ok(points) => points,
+      Err(message) => {
+        errors.push(error(message));
+        continue;
+      }
+    };
+    if points.len() < 2 {
+      errors.push(error("a path needs at least two points".to_string()));
+      continue;
+    }
+
+    for w in points.windows(2) {
+      let (from, to) = (w[0], w[1]);
+      if from == to {
+        errors.push(error(format!("zero length segment at {},{}", from.x, from.y)));
+      } else if from.x != to.x && from.y != to.y {
+        errors.push(error(format!("diagonal segment from {},{} to {},{}", from.x, from.y, to.x, to.y)));
+      }
+    }
+    paths.push(points);
+  }
+
+  if errors.is_empty() {
+    Ok(paths)
+  } else {
+    Err(errors)
+  }
+}
+
+fn parse_point(text: &str) -> Result<Point, String> {
+  let (x, y) = text
+    .split_once(',')
+    .ok_or_else(|| format!("expected a point like 498,4 but found '{}'", text))?;
+  let x = x.trim().parse().map_err(|_| format!("invalid x coordinate in '{}'", text))?;
+  let y = y.trim().parse().map_err(|_| format!("invalid y coordinate in '{}'", text))?;
+  Ok(Point { x, y })
+}
+
+// Every cell covered by the rock paths, marked with `#`
+pub fn paths_to_grid(paths: &[Vec<Point>]) -> HashMap<Point, char> {
+  let mut grid = HashMap::new();
+  for path in paths {
+    for w in path.windows(2) {
+      for x in w[0].x.min(w[1].x)..=w[0].x.max(w[1].x) {
+        for y in w[0].y.min(w[1].y)..=w[0].y.max(w[1].y) {
+          grid.insert(Point { x, y }, '#');
+        }
+      }
+    }
+  }
+  grid
+}
+
+fn bounds(grid: &HashMap<Point, char>) -> (Point, Point) {
+  let min = Point {
+    x: grid.keys().map(|p| p.x).min().unwrap_or(0),
+    y: grid.keys().map(|p| p.y).min().unwrap_or(0),
+  };
+  let max = Point {
+    x: grid.keys().map(|p| p.x).max().unwrap_or(-1),
+    y: grid.keys().map(|p| p.y).max().unwrap_or(-1),
+  };
+  (min, max)
+}
+
+// The cave drawn the same way as the puzzle, cropped to what's in it
+pub fn cave_to_text(grid: &HashMap<Point, char>) -> String {
+  let (min, max) = bounds(grid);
+  let mut text = String::new();
+  for y in min.y..=max.y {
+    for x in min.x..=max.x {
+      text.push(*grid.get(&Point { x, y }).unwrap_or(&'.'));
+    }
+    text.push('\n');
+  }
+  text
+}
+
+// Binary PPM image with one pixel per cell, which pretty much any image viewer
+// can open without us needing an image library
+pub fn cave_to_ppm(grid: &HashMap<Point, char>) -> Vec<u8> {
+  let (min, max) = bounds(grid);
+  let width = (max.x - min.x + 1).max(0);
+  let height = (max.y - min.y + 1).max(0);
+
+  let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+  for y in min.y..=max.y {
+    for x in min.x..=max.x {
+      let colour: [u8; 3] = match grid.get(&Point { x, y }) {
+        None => [20, 20, 30],
+        Some('#') => [120, 110, 100],
+        Some('o') => [230, 200, 110],
+        // Any other material gets a stable colour of its own
+        Some(c) => {
+          let c = *c as u32;
+          [(c * 67 % 256) as u8, (c * 131 % 256) as u8, (c * 199 % 256) as u8]
+        }
+      };
+      image.extend(colour);
+    }
+  }
+  image
+}
+
+// Random rock scans for stress testing. The scan fits in a `width` x `height`
+// box centred under the usual sand source at 500,0, and the same seed always
+// gives the same scan. Every segment turns a corner, so the box has to be at
+// least 2 x 2
+pub fn generate_scan(width: i64, height: i64, path_count: usize, seed: u64) -> Vec<String> {
+  assert!(width >= 2 && height >= 2, "Scans need a box of at least 2 x 2, not {} x {}", width, height);
+  let mut rng = XorShift { state: seed.max(1) };
+  let min_x = 500 - width / 2;
+  let max_x = min_x + width - 1;
+
+  (0..path_count)
+    .map(|_| {
+      let mut point = Point {
+        x: min_x + rng.below(width as u64) as i64,
+        y: 1 + rng.below((height - 1) as u64) as i64,
+      };
+      let mut points = vec![point];
+      let segments = 1 + rng.below(4);
+      for segment in 0..segments {
+        // Alternate between horizontal and vertical so no segment is zero
+        // length or continues straight on from the last one
+        let length = 1 + rng.below(8) as i64;
+        let sign = if rng.below(2) == 0 { -1 } else { 1 };
+        let mut next = point;
+        if segment % 2 == 0 {
+          next.x = (point.x + sign * length).clamp(min_x, max_x);
+          if next.x == point.x {
+            next.x = if point.x == min_x { point.x + 1 } else { point.x - 1 };
+          }
+        } else {
+          next.y = (point.y + sign * length).clamp(1, height);
+          if next.y == point.y {
+            next.y = if point.y == 1 { point.y + 1 } else { point.y - 1 };
+          }
+        }
+        points.push(next);
+        point = next;
+      }
+
+      points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<String>>()
+        .join(" -> ")
+    })
+    .collect()
+}
+
+struct XorShift {
+  state: u64,
+}
+
+impl XorShift {
+  fn below(&mut self, max: u64) -> u64 {
+    self.state ^= self.state << 13;
+    self.state ^= self.state >> 7;
+    self.state ^= self.state << 17;
+    self.state % max.max(1)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::day08::Point;
+  use crate::day14_scan::{cave_to_ppm, cave_to_text, generate_scan, parse_scan, paths_to_grid, ScanError};
+
+  #[test]
+  fn test_parse_sample_scan() {
+    let paths = parse_scan(&get_input()).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[1][3], Point { x: 494, y: 9 });
+    assert_eq!(paths_to_grid(&paths).len(), 20);
+  }
+
+  #[test]
+  fn test_validation_errors() {
+    let lines: Vec<String> = [
+      "498,4 -> 498,6",
+      "498,4 -> 499,5",
+      "",
+      "1,1 -> 1,1 -> 3,1",
+      "1,1 -> x,2",
+      "7,7",
+    ].iter().map(|l| l.to_string()).collect();
+
+    let errors = parse_scan(&lines).unwrap_err();
+    assert_eq!(errors, vec![
+      ScanError { line: 2, message: "diagonal segment from 498,4 to 499,5".to_string() },
+      ScanError { line: 4, message: "zero length segment at 1,1".to_string() },
+      ScanError { line: 5, message: "invalid x coordinate in 'x,2'".to_string() },
+      ScanError { line: 6, message: "a path needs at least two points".to_string() },
+    ]);
+    assert_eq!(errors[0].to_string(), "line 2: diagonal segment from 498,4 to 499,5");
+  }
+
+  #[test]
+  fn test_export() {
+    let grid = paths_to_grid(&parse_scan(&get_input()).unwrap());
+    let text = cave_to_text(&grid);
+    assert_eq!(text.lines().next().unwrap(), "....#...##");
+    assert_eq!(text.lines().last().unwrap(), "#########.");
+
+    let image = cave_to_ppm(&grid);
+    let header = "P6\n10 6\n255\n";
+    assert!(image.starts_with(header.as_bytes()));
+    assert_eq!(image.len(), header.len() + 10 * 6 * 3);
+  }
+
+  #[test]
+  fn test_generated_scans_are_valid() {
+    let scan = generate_scan(60, 40, 25, 7);
+    assert_eq!(scan.len(), 25);
+    assert_eq!(scan, generate_scan(60, 40, 25, 7));
+
+    let paths = parse_scan(&scan).unwrap();
+    for p in paths.iter().flatten() {
+      assert!(p.x >= 470 && p.x < 530);
+      assert!(p.y >= 1 && p.y <= 40);
+    }
+  }
+
+  #[test]
+  fn test_generated_scans_in_smallest_box() {
+    let paths = parse_scan(&generate_scan(2, 2, 50, 3)).unwrap();
+    for p in paths.iter().flatten() {
+      assert!(p.x == 499 || p.x == 500);
+      assert!(p.y == 1 || p.y == 2);
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "Scans need a box of at least 2 x 2, not 1 x 10")]
+  fn test_generated_scan_too_narrow() {
+    generate_scan(1, 10, 5, 3);
+  }
+
+  fn get_input() -> Vec<String> {
+    vec![
+      "498,4 -> 498,6 -> 496,6".to_owned(),
+      "503,4 -> 502,4 -> 502,9 -> 494,9".to_owned(),
+    ]
+  }
+}
@@ -1,187 +1,39 @@
 use std::collections::{HashMap, VecDeque};
 
-use crate::day08::Point;
 use crate::day17::JetBlastDirection::{Left, Right};
 use crate::utils::read_chunks;
 
 pub fn part_one() -> u64 {
   let lines = read_chunks("day17.txt", "\n");
   let jet_blasts = parse_input(&lines);
-  let grid = drop_shapes(&jet_blasts, 2022);
-  find_highest_point(&grid)
-}
-
-fn find_highest_point(grid: &HashMap<Point, char>) -> u64 {
-  grid.keys().map(|k| k.y).max().unwrap() as u64
+  let mut chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+  chamber.drop_rocks(2022);
+  chamber.height()
 }
 
 pub fn part_two() -> u64 {
-  let lines = read_chunks("day17.txt", "\n");
-  let mut jet_blasts = parse_input(&lines);
-  let mut shapes = get_shapes();
-  let grid_to_analyze = drop_shapes(&jet_blasts, 5_000);
-  let analyze_result = analyze_find_first_repeating_index_delta(&grid_to_analyze);
-  let mut grid = HashMap::new();
-
-  calculate_height_after_dropping_many_shapes(
-    &lines,
-    1_000_000_000_000,
-    analyze_result,
-    &mut jet_blasts,
-    &mut shapes,
-    &mut grid,
-  )
-}
-
-fn find_full_line(grid: &HashMap<Point, char>, y_min: u64) -> u64 {
-  let height = grid.keys().map(|k| k.y).max().unwrap() as u64;
-  for y in y_min..=height {
-    if check_if_row_is_new_floor(grid, y) {
-      return y;
-    }
-  }
-
-  panic!("Never found a full row");
-}
-
-struct AnalyzeResult {
-  offset: u64,
-  period: u64,
-}
-
-fn analyze_find_first_repeating_index_delta(grid: &HashMap<Point, char>) -> AnalyzeResult {
-  let highest_point = find_highest_point(&grid);
-
-  let mut delta_to_line: HashMap<u64, u64> = HashMap::new();
-
-  let mut full_line = find_full_line(&grid, 1);
-  let mut repeated_delta_between_lines_prev = 0;
-
-  loop {
-    let next_full_line = find_full_line(&grid, full_line + 1);
-    let delta = next_full_line - full_line;
-
-    let prev_line = delta_to_line.get(&delta);
-    match prev_line {
-      None => {}
-      Some(line) => {
-        let count_of_lines = next_full_line - line;
-        if repeated_delta_between_lines_prev > 0 {
-          if repeated_delta_between_lines_prev == count_of_lines {
-            return AnalyzeResult {
-              offset: full_line,
-              period: repeated_delta_between_lines_prev,
-            };
-          } else {
-            repeated_delta_between_lines_prev = 0;
-          }
-        } else {
-          repeated_delta_between_lines_prev = count_of_lines;
-        }
-      }
-    }
-
-    delta_to_line.insert(delta, next_full_line);
-    full_line = next_full_line;
-    if full_line > highest_point {
-      break;
-    }
-  }
-  panic!("Couldn't find a repeating pattern")
+  tower_heights().height_after(1_000_000_000_000).expect("Never found a repeating cycle") as u64
 }
 
-fn calculate_height_after_dropping_many_shapes(
-  lines: &Vec<String>,
-  target_num_shapes_to_drop: u64,
-  analyze_result: AnalyzeResult,
-  mut jet_blasts: &mut VecDeque<JetBlastDirection>,
-  mut shapes: &mut VecDeque<Vec<Point>>,
-  mut grid: &mut HashMap<Point, char>,
-) -> u64 {
-  assert!(target_num_shapes_to_drop >= 10_000, "Must be at least 10k to drop to use this function");
-
-  let mut found_first = false;
-  let mut shapes_to_first_floor = 0;
-  let mut height_at_first_floor = 0;
-  let mut found_second = false;
-  let mut shapes_to_second_floor = 0;
-  let mut height_at_second_floor = 0;
-  let mut count_dropped_shapes = 0;
-
-  loop {
-    drop_one_shape(
-      &mut jet_blasts,
-      &mut shapes,
-      &mut grid,
-    );
-    count_dropped_shapes += 1;
-
-    if check_if_row_is_new_floor(&grid, analyze_result.offset) && !found_first {
-      found_first = true;
-      shapes_to_first_floor = count_dropped_shapes;
-      height_at_first_floor = find_highest_point(&grid);
-      println!("Reached first new floor with # shapes: {}", count_dropped_shapes);
-    }
-    if check_if_row_is_new_floor(&grid, analyze_result.offset + analyze_result.period) && !found_second {
-      found_second = true;
-      shapes_to_second_floor = count_dropped_shapes;
-      height_at_second_floor = find_highest_point(&grid);
-      println!("Reached second new floor with # shapes: {}", count_dropped_shapes);
-    }
-
-    if found_first && found_second {
-      break;
-    }
-
-    if count_dropped_shapes > 5_000 {
-      panic!("Couldn't determine the period!")
-    }
-  }
-
-  if shapes_to_first_floor == 0 || shapes_to_second_floor == 0 {
-    panic!("Didn't determine the required shapes to the first and second floor");
-  }
-
-  if height_at_first_floor == 0 || height_at_second_floor == 0 {
-    panic!("Couldn't figure out height at first and second floor");
-  }
-
-  let initial_required_shapes = shapes_to_first_floor;
-  let shapes_required_before_repeat = shapes_to_second_floor - shapes_to_first_floor;
-
-  let height_delta_between_floors = height_at_second_floor - height_at_first_floor;
-
-  let remainder_after_repeat = (target_num_shapes_to_drop - initial_required_shapes) % shapes_required_before_repeat;
-  let full_repeats = (target_num_shapes_to_drop - initial_required_shapes) / shapes_required_before_repeat;
-
+// Heights for the puzzle input, with the cycle worked out so any number of
+// rocks can be answered
+pub fn tower_heights() -> TowerHeights {
+  let lines = read_chunks("day17.txt", "\n");
   let jet_blasts = parse_input(&lines);
-  let final_grid_2 = drop_shapes(&jet_blasts, initial_required_shapes + remainder_after_repeat);
-  let remainder_highest_point = find_highest_point(&final_grid_2);
-
-  let result = (full_repeats * height_delta_between_floors) + remainder_highest_point;
-  result
+  let max_rocks = (10 * jet_blasts.len() * default_shapes().len()).max(10_000) as u64;
+  let chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+  TowerHeights::analyze(chamber, max_rocks)
 }
 
-
-fn check_if_row_is_new_floor(grid: &HashMap<Point, char>, y: u64) -> bool {
-  let count = grid
-    .iter()
-    .filter(|(k, _)| (*k).y == y as i64)
-    .filter(|(_, v)| **v == '#')
-    .count();
-
-  count == 7
-}
-
-#[derive(Clone, Debug)]
-enum JetBlastDirection {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JetBlastDirection {
   Left,
   Right,
 }
 
-fn parse_input(lines: &Vec<String>) -> VecDeque<JetBlastDirection> {
+pub fn parse_input(lines: &[String]) -> VecDeque<JetBlastDirection> {
   lines
-    .get(0).unwrap()
+    .first().unwrap()
     .split("")
     .filter(|s| !s.is_empty())
     .map(|v| {
@@ -197,231 +49,401 @@ fn parse_input(lines: &Vec<String>) -> VecDeque<JetBlastDirection> {
 }
 
 
-fn drop_shapes(input_jet_blasts: &VecDeque<JetBlastDirection>, max_shapes_to_drop: u64) -> HashMap<Point, char> {
-  let mut jet_blasts = input_jet_blasts.clone();
-  let mut shapes = get_shapes();
-  let mut grid: HashMap<Point, char> = HashMap::new();
+const PUZZLE_SHAPES: &str = "####
 
-  for _ in 0..max_shapes_to_drop {
-    drop_one_shape(&mut jet_blasts, &mut shapes, &mut grid)
-  }
+.#.
+###
+.#.
+
+..#
+..#
+###
 
-  // dbg_print_grid(&grid, None);
-  grid
+#
+#
+#
+#
+
+##
+##";
+
+// A rock stored as one bitmask per row, bottom row first. Bit `x` is set when
+// the rock fills column `x`, counting from the rock's left edge
+#[derive(Clone, Debug, PartialEq)]
+pub struct RockShape {
+  pub rows: Vec<u64>,
+  pub width: usize,
 }
 
-fn drop_one_shape(
-  jet_blasts: &mut VecDeque<JetBlastDirection>,
-  shapes: &mut VecDeque<Vec<Point>>,
-  grid: &mut HashMap<Point, char>,
-) {
-  let left = Point { x: -1, y: 0 };
-  let right = Point { x: 1, y: 0 };
-  let down = Point { x: 0, y: -1 };
-
-  let min_x = 0;
-  let max_x = 6;
-  let min_y = 0;
-
-
-  // Determine highest current point
-  let curr_highest_y = grid.keys().map(|p| p.y).max().or(Some(0)).unwrap();
-
-  // Get next shape and update the shape generator
-  let next_shape_raw = shapes.front().unwrap().clone();
-  shapes.rotate_left(1);
-
-  // Position the shape correctly
-  let mut shape_location: Vec<Point> = next_shape_raw
-    .iter()
-    .map(|p| *p + Point { x: 2, y: curr_highest_y + 4 })
-    .collect();
-
-  // println!("Starting location");
-  // dbg_print_grid(&grid, Some(&shape_location));
-
-  // Loop through jet + move down blast until it settles
-  loop {
-    // Let's see if we can move left/right according to our next jet blast
-    let next_jet_blast = jet_blasts.front().unwrap().clone();
-    jet_blasts.rotate_left(1);
-    // println!("Jet blast: {:?}", next_jet_blast);
-
-    let next_shape_location: Vec<Point> = match next_jet_blast {
-      Left => {
-        shape_location
-          .iter()
-          .map(|p| *p + left)
-          .collect()
-      }
-      Right => {
-        shape_location
-          .iter()
-          .map(|p| *p + right)
-          .collect()
+pub fn default_shapes() -> Vec<RockShape> {
+  parse_shapes(PUZZLE_SHAPES).unwrap()
+}
+
+// Shapes drawn the same way as the puzzle, `#` for rock and `.` for air, with a
+// blank line between each shape. Empty columns on the left and empty rows at
+// the top and bottom are trimmed so the spawn offset and gap always line up
+// with the rock itself
+pub fn parse_shapes(text: &str) -> Result<Vec<RockShape>, String> {
+  text
+    .split("\n\n")
+    .map(|block| block.trim_matches('\n'))
+    .filter(|block| !block.trim().is_empty())
+    .enumerate()
+    .map(|(i, block)| {
+      let mut rows = block
+        .lines()
+        .map(|line| {
+          line.trim_end().chars().enumerate().try_fold(0u64, |row, (x, c)| match c {
+            '#' if x < 64 => Ok(row | 1 << x),
+            '#' => Err(format!("shape {} is wider than 64 columns", i + 1)),
+            '.' => Ok(row),
+            other => Err(format!("shape {} has unexpected character '{}'", i + 1, other)),
+          })
+        })
+        .collect::<Result<Vec<u64>, String>>()?;
+      rows.reverse();
+
+      let all_columns = rows.iter().fold(0, |all, row| all | row);
+      if all_columns == 0 {
+        return Err(format!("shape {} has no rock in it", i + 1));
       }
-    };
+      let bottom = rows.iter().position(|row| *row != 0).unwrap();
+      let top = rows.iter().rposition(|row| *row != 0).unwrap();
+      let shift = all_columns.trailing_zeros();
+      let rows: Vec<u64> = rows[bottom..=top].iter().map(|row| row >> shift).collect();
+      let width = (64 - (all_columns >> shift).leading_zeros()) as usize;
+      Ok(RockShape { rows, width })
+    })
+    .collect()
+}
 
-    // Count up the collisions in this new location
-    let collisions = next_shape_location
-      .iter()
-      .map(|p| grid.get(p))
-      .filter(|v| v.is_some())
-      .count();
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChamberConfig {
+  // Up to 64 columns, one bit each
+  pub width: usize,
+  // Gap between the left wall and a new rock
+  pub spawn_left: usize,
+  // Empty rows between the top of the tower and a new rock
+  pub spawn_gap: u64,
+}
 
-    // Count up out of bounds in this new location
-    let out_of_bounds = next_shape_location
-      .iter()
-      .filter(|p| p.x < min_x || p.x > max_x)
-      .count();
+impl Default for ChamberConfig {
+  fn default() -> Self {
+    ChamberConfig { width: 7, spawn_left: 2, spawn_gap: 3 }
+  }
+}
+
+// Where a rock came to rest, `x` and `y` are the bottom left of the shape with
+// row 0 being the first row above the floor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RockEvent {
+  pub rock: u64,
+  pub shape: usize,
+  pub x: usize,
+  pub y: u64,
+  pub first_jet: usize,
+  pub jets_used: usize,
+  pub height: u64,
+}
 
+// The tower as one bitmask per row, lowest row first. Rocks and jets are used
+// in order, wrapping around when they run out
+pub struct Chamber {
+  rows: Vec<u64>,
+  config: ChamberConfig,
+  shapes: Vec<RockShape>,
+  jets: Vec<JetBlastDirection>,
+  next_shape: usize,
+  next_jet: usize,
+  rocks_dropped: u64,
+}
 
-    // If we don't have a collision and we're not out of bounds,
-    // then this is our next location for the loop
-    if collisions == 0 && out_of_bounds == 0 {
-      shape_location = next_shape_location;
-      // dbg_print_grid(&grid, Some(&shape_location));
+impl Chamber {
+  pub fn new(shapes: Vec<RockShape>, jets: Vec<JetBlastDirection>, config: ChamberConfig) -> Chamber {
+    assert!(config.width > 0 && config.width <= 64, "Chamber width must be between 1 and 64");
+    assert!(!shapes.is_empty(), "Need at least one shape to drop");
+    assert!(!jets.is_empty(), "Need at least one jet");
+    for shape in &shapes {
+      assert!(config.spawn_left + shape.width <= config.width, "Shape doesn't fit in the chamber where it spawns");
     }
 
-    // println!("Moving down");
+    Chamber { rows: vec![], config, shapes, jets, next_shape: 0, next_jet: 0, rocks_dropped: 0 }
+  }
 
-    // Now, check if we can move downwards
-    // If not, we're done!
-    let next_shape_location: Vec<Point> = shape_location
-      .iter()
-      .map(|p| *p + down)
-      .collect();
+  pub fn height(&self) -> u64 {
+    self.rows.len() as u64
+  }
 
-    // Count up the collisions in this new location
-    let collisions = next_shape_location
-      .iter()
-      .map(|p| grid.get(p))
-      .filter(|v| v.is_some())
-      .count();
+  // How far down from the top of the tower the first rock in each column is,
+  // capped at `max_depth` so a deep empty column doesn't stop the surface from
+  // ever repeating
+  pub fn surface_profile(&self, max_depth: u64) -> Vec<u64> {
+    (0..self.config.width)
+      .map(|x| {
+        self.rows
+          .iter()
+          .rev()
+          .take(max_depth as usize)
+          .position(|row| row & (1 << x) != 0)
+          .map(|depth| depth as u64)
+          .unwrap_or(max_depth)
+      })
+      .collect()
+  }
 
-    // Count up out of bounds in this new location
-    let out_of_bounds = next_shape_location
+  fn fits(&self, shape: &RockShape, x: usize, y: u64) -> bool {
+    shape
+      .rows
       .iter()
-      .filter(|p| p.y <= min_y)
-      .count();
-
-    // If we have a collision or are out of bounds, then the
-    // last location is where we're settling
-    if collisions > 0 || out_of_bounds > 0 {
-      shape_location
-        .iter()
-        .for_each(|p| {
-          grid.insert(p.clone(), '#');
-        });
-      // println!("Settled location");
-      // dbg_print_grid(&grid, None);
-      break;
+      .enumerate()
+      .all(|(i, row)| self.rows.get(y as usize + i).is_none_or(|filled| filled & (row << x) == 0))
+  }
+
+  pub fn drop_rock(&mut self) -> RockEvent {
+    let shape_index = self.next_shape;
+    let shape = &self.shapes[shape_index];
+    let first_jet = self.next_jet;
+    let mut jets_used = 0;
+    let mut x = self.config.spawn_left;
+    let mut y = self.height() + self.config.spawn_gap;
+
+    loop {
+      let jet = self.jets[self.next_jet];
+      self.next_jet = (self.next_jet + 1) % self.jets.len();
+      jets_used += 1;
+
+      match jet {
+        Left if x > 0 && self.fits(shape, x - 1, y) => x -= 1,
+        Right if x + shape.width < self.config.width && self.fits(shape, x + 1, y) => x += 1,
+        _ => {}
+      }
+
+      if y == 0 || !self.fits(shape, x, y - 1) {
+        break;
+      }
+      y -= 1;
+    }
+
+    let shape = self.shapes[shape_index].clone();
+    for (i, row) in shape.rows.iter().enumerate() {
+      let row_y = y as usize + i;
+      if row_y >= self.rows.len() {
+        self.rows.resize(row_y + 1, 0);
+      }
+      self.rows[row_y] |= row << x;
     }
 
-    // Otherwise this is our next location
-    shape_location = next_shape_location;
-    // dbg_print_grid(&grid, Some(&shape_location));
+    let event = RockEvent {
+      rock: self.rocks_dropped,
+      shape: shape_index,
+      x,
+      y,
+      first_jet,
+      jets_used,
+      height: self.height(),
+    };
+    self.next_shape = (self.next_shape + 1) % self.shapes.len();
+    self.rocks_dropped += 1;
+    event
   }
-}
 
-#[allow(dead_code)]
-pub fn dbg_print_grid(grid: &HashMap<Point, char>, intermediate_rock: Option<&Vec<Point>>) {
-  let mut grid_copy = grid.clone();
-  if intermediate_rock.is_some() {
-    intermediate_rock.unwrap()
+  pub fn drop_rocks(&mut self, count: u64) -> Vec<RockEvent> {
+    (0..count).map(|_| self.drop_rock()).collect()
+  }
+
+  // Same picture as the puzzle, top of the tower first
+  pub fn render(&self) -> String {
+    let mut lines: Vec<String> = self
+      .rows
       .iter()
-      .for_each(|p| {
-        grid_copy.insert(p.clone(), '@');
+      .rev()
+      .map(|row| {
+        let cells: String = (0..self.config.width).map(|x| if row & (1 << x) != 0 { '#' } else { '.' }).collect();
+        format!("|{}|", cells)
       })
+      .collect();
+    lines.push(format!("+{}+", "-".repeat(self.config.width)));
+    lines.join("\n")
   }
+}
 
-  let max_y = grid_copy.keys().map(|k| k.y).max().unwrap();
+// How many rows of the surface to look at when deciding if the chamber is
+// back in a state it's been in before
+const PROFILE_DEPTH: u64 = 64;
+
+// Every `period` rocks after the first `offset` rocks, the tower grows by
+// exactly `height_per_period`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cycle {
+  pub offset: u64,
+  pub period: u64,
+  pub height_per_period: u64,
+}
+
+pub struct TowerHeights {
+  // Height after n rocks, starting from 0 rocks
+  heights: Vec<u64>,
+  cycle: Option<Cycle>,
+}
 
-  for y in (0..=max_y).rev() {
-    for x in -1..=7 {
-      if y == 0 && (x == -1 || x == 7) {
-        print!("+");
-      } else if y == 0 && x != -1 && x != 7 {
-        print!("-");
+impl TowerHeights {
+  // Drops rocks until the next shape, next jet and the surface of the tower
+  // are the same as they were before. That's only a candidate, so the cycle is
+  // confirmed by checking the tower grows the same way for a whole extra period
+  // before it's accepted. Gives up after `max_rocks`
+  pub fn analyze(mut chamber: Chamber, max_rocks: u64) -> TowerHeights {
+    let mut heights = vec![chamber.height()];
+    let mut seen: HashMap<(usize, usize, Vec<u64>), u64> = HashMap::new();
+    let mut candidate: Option<(u64, u64)> = None;
+
+    for rocks in 0..=max_rocks {
+      if let Some((start, period)) = candidate {
+        if rocks == start + 2 * period {
+          let growth = heights[(start + period) as usize] - heights[start as usize];
+          let confirmed = (start..start + period)
+            .all(|k| heights[(k + period) as usize] - heights[k as usize] == growth);
+          if confirmed {
+            let cycle = Cycle { offset: start, period, height_per_period: growth };
+            return TowerHeights { heights, cycle: Some(cycle) };
+          }
+          candidate = None;
+        }
       }
-      if y != 0 && (x == -1 || x == 7) {
-        print!("|");
+      if rocks == max_rocks {
+        break;
       }
-      if y != 0 && x != -1 && x != 7 {
-        print!("{}", grid_copy.get(&Point { x, y }).unwrap_or(&'.'));
+
+      let key = (chamber.next_shape, chamber.next_jet, chamber.surface_profile(PROFILE_DEPTH));
+      if candidate.is_none() {
+        candidate = seen.get(&key).map(|start| (*start, rocks - start));
       }
+      seen.insert(key, rocks);
+
+      chamber.drop_rock();
+      heights.push(chamber.height());
     }
-    println!();
+
+    TowerHeights { heights, cycle: None }
   }
-  println!();
-}
 
-fn get_shapes() -> VecDeque<Vec<Point>> {
-  let shapes_vec = vec![
-    // ####
-    vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 2, y: 0 }, Point { x: 3, y: 0 }],
+  pub fn cycle(&self) -> Option<Cycle> {
+    self.cycle
+  }
 
-    // .#.
-    // ###
-    // .#.
-    vec![
-      Point { x: 1, y: 2 },
-      Point { x: 0, y: 1 }, Point { x: 1, y: 1 }, Point { x: 2, y: 1 },
-      Point { x: 1, y: 0 },
-    ],
-
-    // ..#
-    // ..#
-    // ###
-    vec![
-      Point { x: 2, y: 2 },
-      Point { x: 2, y: 1 },
-      Point { x: 0, y: 0 }, Point { x: 1, y: 0 }, Point { x: 2, y: 0 },
-    ],
-
-    // #
-    // #
-    // #
-    // #
-    vec![
-      Point { x: 0, y: 3 },
-      Point { x: 0, y: 2 },
-      Point { x: 0, y: 1 },
-      Point { x: 0, y: 0 },
-    ],
-
-    // ##
-    // ##
-    vec![
-      Point { x: 0, y: 1 }, Point { x: 1, y: 1 },
-      Point { x: 0, y: 0 }, Point { x: 1, y: 0 },
-    ],
-  ];
+  // `None` when `rocks` is past what was simulated and no cycle was found
+  pub fn height_after(&self, rocks: u64) -> Option<u128> {
+    if let Some(height) = self.heights.get(rocks as usize) {
+      return Some(*height as u128);
+    }
 
-  VecDeque::from_iter(shapes_vec)
+    let cycle = self.cycle?;
+    let full_periods = (rocks - cycle.offset) / cycle.period;
+    let remainder = (rocks - cycle.offset) % cycle.period;
+    let partial_height = self.heights[(cycle.offset + remainder) as usize] as u128;
+    Some(partial_height + full_periods as u128 * cycle.height_per_period as u128)
+  }
 }
 
+impl Iterator for Chamber {
+  type Item = RockEvent;
+
+  fn next(&mut self) -> Option<RockEvent> {
+    Some(self.drop_rock())
+  }
+}
 
 #[cfg(test)]
 mod tests {
-  use std::collections::HashMap;
-
   use crate::day17::{
-    analyze_find_first_repeating_index_delta,
-    calculate_height_after_dropping_many_shapes,
-    drop_shapes, find_highest_point,
-    get_shapes,
+    Chamber,
+    ChamberConfig,
+    default_shapes,
     parse_input,
+    parse_shapes,
+    TowerHeights,
   };
   use crate::utils::read_chunks;
 
   #[test]
   fn test_part_1() {
-    let input = get_input();
-    let jet_blasts = parse_input(&input);
-    let final_grid = drop_shapes(&jet_blasts, 2022);
-    let highest_point = find_highest_point(&final_grid);
-    assert_eq!(highest_point, 3068);
+    let jet_blasts = parse_input(&get_input());
+    let mut chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+    chamber.drop_rocks(2022);
+    assert_eq!(chamber.height(), 3068);
+  }
+
+  #[test]
+  fn test_part_2() {
+    let jet_blasts = parse_input(&get_input());
+    let chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+    let heights = TowerHeights::analyze(chamber, 10_000);
+    assert_eq!(heights.cycle().unwrap().period, 35);
+    assert_eq!(heights.height_after(2022), Some(3068));
+    assert_eq!(heights.height_after(1_000_000_000_000), Some(1514285714288));
+    assert!(heights.height_after(u64::MAX).unwrap() > u64::MAX as u128);
+  }
+
+  #[test]
+  fn test_cycle_matches_simulation() {
+    // Jets that never complete a full row still settle into a cycle
+    let jets = Vec::from(parse_input(&vec!["<".to_string()]));
+    let chamber = Chamber::new(default_shapes(), jets.clone(), ChamberConfig::default());
+    let heights = TowerHeights::analyze(chamber, 1_000);
+    assert!(heights.cycle().is_some());
+
+    let mut chamber = Chamber::new(default_shapes(), jets, ChamberConfig::default());
+    chamber.drop_rocks(5_000);
+    assert_eq!(heights.height_after(5_000), Some(chamber.height() as u128));
+  }
+
+  #[test]
+  fn test_no_cycle_within_limit() {
+    let jet_blasts = parse_input(&get_input());
+    let chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+    let heights = TowerHeights::analyze(chamber, 20);
+    assert_eq!(heights.cycle(), None);
+    assert!(heights.height_after(20).is_some());
+    assert_eq!(heights.height_after(21), None);
+  }
+
+  #[test]
+  fn test_rock_events() {
+    let jet_blasts = parse_input(&get_input());
+    let chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+    let events: Vec<_> = chamber.take(3).collect();
+
+    // First rock is pushed right once, then left three times, and lands on the floor
+    assert_eq!(events[0].shape, 0);
+    assert_eq!((events[0].x, events[0].y), (2, 0));
+    assert_eq!((events[0].first_jet, events[0].jets_used), (0, 4));
+    assert_eq!(events[0].height, 1);
+
+    assert_eq!(events[1].shape, 1);
+    assert_eq!(events[1].first_jet, 4);
+    assert_eq!(events[1].height, 4);
+    assert_eq!(events[2].rock, 2);
+    assert_eq!(events[2].height, 6);
+  }
+
+  #[test]
+  fn test_parse_shapes_and_render() {
+    let shapes = parse_shapes("..##\n..#.\n\n\n#\n").unwrap();
+    assert_eq!(shapes.len(), 2);
+    assert_eq!(shapes[0].rows, vec![0b01, 0b11]);
+    assert_eq!(shapes[0].width, 2);
+    assert_eq!(shapes[1].rows, vec![1]);
+    let padded = parse_shapes("...\n.#.\n...\n##.\n...\n...").unwrap();
+    assert_eq!(padded[0].rows, vec![0b11, 0b00, 0b10]);
+    assert_eq!(padded[0].width, 2);
+    assert!(parse_shapes("#x#").is_err());
+    assert!(parse_shapes("...").is_err());
+    assert_eq!(default_shapes().len(), 5);
+
+    let jets = Vec::from(parse_input(&get_input()));
+    let config = ChamberConfig { width: 4, spawn_left: 1, spawn_gap: 1 };
+    let mut chamber = Chamber::new(shapes, jets, config);
+    chamber.drop_rocks(3);
+    assert_eq!(chamber.render(), "|.##.|\n|.###|\n|.##.|\n+----+");
   }
 
   #[test]
@@ -430,18 +452,14 @@ mod tests {
     let lines = read_chunks("day17.txt", "\n");
     let jet_blasts = parse_input(&lines);
     let target_num_shapes_to_drop = 10_000;
-    let final_grid = drop_shapes(&jet_blasts, target_num_shapes_to_drop);
-    let actual_highest_point = find_highest_point(&final_grid);
-    let analyze_result = analyze_find_first_repeating_index_delta(&final_grid);
-
-
-    let mut jet_blasts = parse_input(&lines);
-    let mut shapes = get_shapes();
-    let mut grid = HashMap::new();
+    let mut chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts.clone()), ChamberConfig::default());
+    chamber.drop_rocks(target_num_shapes_to_drop);
+    let actual_highest_point = chamber.height() as u128;
 
-    let result = calculate_height_after_dropping_many_shapes(&lines, target_num_shapes_to_drop, analyze_result, &mut jet_blasts, &mut shapes, &mut grid);
+    let chamber = Chamber::new(default_shapes(), Vec::from(jet_blasts), ChamberConfig::default());
+    let heights = TowerHeights::analyze(chamber, 5_000);
 
-    assert_eq!(result, actual_highest_point);
+    assert_eq!(heights.height_after(target_num_shapes_to_drop), Some(actual_highest_point));
   }
 
   fn get_input() -> Vec<String> {
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashSet;
 use std::ops::Add;
 use std::str::Split;
 
@@ -17,101 +17,195 @@ pub fn part_two() -> u64 {
   solve_part_2(&points)
 }
 
-fn solve_part_2(points: &Vec<Point3>) -> u64 {
-  let empty_fill_grid = fill_grid_with_surrounding_cube(&points);
-  let filled_grid = fill_grid_with_points(&points, &empty_fill_grid);
-  let cleared_exterior_grid = clear_exterior_grid_points(&filled_grid);
-  let interior_surface_area = compute_surface_area_of_interior_space(&cleared_exterior_grid);
-  let exterior_surface_area = find_surface_area(&points) - interior_surface_area;
+fn solve_part_2(points: &[Point3]) -> u64 {
+  VoxelGrid::new(points).exterior_surface_area()
+}
 
-  exterior_surface_area
+pub fn voxel_grid() -> VoxelGrid {
+  let lines = read_chunks("day18.txt", "\n");
+  let points = parse_input(&lines);
+  VoxelGrid::new(&points)
 }
 
-fn compute_surface_area_of_interior_space(cleared_exterior_grid: &HashMap<Point3, char>) -> u64 {
-  let interior_cubes = cleared_exterior_grid
-    .iter()
-    .filter(|(_, v)| **v == '.')
-    .map(|(k, _)| k.clone())
-    .collect::<Vec<Point3>>();
+// Analysis of the puzzle input's droplet
+pub fn voxel_report() -> VoxelReport {
+  voxel_grid().analyze()
+}
 
-  find_surface_area(&interior_cubes)
+// A connected group of cubes that are all lava or all air. `surface_area`
+// counts faces shared with the other material, so for an air pocket it's the
+// lava surface facing into the pocket
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Component {
+  pub volume: u64,
+  pub min: Point3,
+  pub max: Point3,
+  pub surface_area: u64,
 }
 
-fn clear_exterior_grid_points(filled_grid: &HashMap<Point3, char>) -> HashMap<Point3, char> {
-  let min_x = filled_grid.keys().map(|k| k.x).min().unwrap();
-  let min_y = filled_grid.keys().map(|k| k.y).min().unwrap();
-  let min_z = filled_grid.keys().map(|k| k.z).min().unwrap();
-  let search_start = Point3 { x: min_x, y: min_y, z: min_z };
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoxelReport {
+  pub lava_volume: u64,
+  // Lava plus any air completely sealed inside it
+  pub enclosed_volume: u64,
+  pub surface_area: u64,
+  pub exterior_surface_area: u64,
+  pub interior_surface_area: u64,
+  pub droplets: Vec<Component>,
+  pub air_pockets: Vec<Component>,
+}
 
-  let mut cleared_grid = filled_grid.clone();
-  cleared_grid.insert(search_start.clone(), ' ');
+// Every cube in the bounding box (plus a layer of air all the way around) as
+// one bit, set for lava. The padding guarantees the corner cube is outside air.
+// The air is labelled up front since every question about the outside needs it
+pub struct VoxelGrid {
+  min: Point3,
+  size: [usize; 3],
+  bits: Vec<u64>,
+  air_labels: Vec<u32>,
+  air: Vec<Component>,
+}
 
-  let mut to_visit: VecDeque<Point3> = VecDeque::new();
-  let mut visited: HashSet<Point3> = HashSet::new();
+impl VoxelGrid {
+  pub fn new(points: &[Point3]) -> VoxelGrid {
+    let min = Point3 {
+      x: points.iter().map(|p| p.x).min().unwrap_or(0) - 1,
+      y: points.iter().map(|p| p.y).min().unwrap_or(0) - 1,
+      z: points.iter().map(|p| p.z).min().unwrap_or(0) - 1,
+    };
+    let size = [
+      (points.iter().map(|p| p.x).max().unwrap_or(0) + 2 - min.x) as usize,
+      (points.iter().map(|p| p.y).max().unwrap_or(0) + 2 - min.y) as usize,
+      (points.iter().map(|p| p.z).max().unwrap_or(0) + 2 - min.z) as usize,
+    ];
+
+    let mut grid = VoxelGrid { min, size, bits: vec![0; (size[0] * size[1] * size[2]).div_ceil(64)], air_labels: vec![], air: vec![] };
+    for p in points {
+      let i = grid.index(p).unwrap();
+      grid.bits[i / 64] |= 1 << (i % 64);
+    }
+    (grid.air_labels, grid.air) = grid.label_components(false);
+    grid
+  }
 
-  to_visit.push_back(search_start.clone());
+  fn len(&self) -> usize {
+    self.size[0] * self.size[1] * self.size[2]
+  }
 
-  while !to_visit.is_empty() {
-    let current = to_visit.pop_front().unwrap();
+  fn index(&self, p: &Point3) -> Option<usize> {
+    let x = p.x - self.min.x;
+    let y = p.y - self.min.y;
+    let z = p.z - self.min.z;
+    if x < 0 || y < 0 || z < 0 || x as usize >= self.size[0] || y as usize >= self.size[1] || z as usize >= self.size[2] {
+      return None;
+    }
+    Some(x as usize + self.size[0] * (y as usize + self.size[1] * z as usize))
+  }
 
-    if visited.contains(&current) {
-      continue;
+  fn point(&self, i: usize) -> Point3 {
+    Point3 {
+      x: self.min.x + (i % self.size[0]) as i64,
+      y: self.min.y + (i / self.size[0] % self.size[1]) as i64,
+      z: self.min.z + (i / (self.size[0] * self.size[1])) as i64,
     }
+  }
+
+  fn is_lava_index(&self, i: usize) -> bool {
+    self.bits[i / 64] & (1 << (i % 64)) != 0
+  }
+
+  // Face neighbours that are inside the grid
+  fn neighbors(&self, i: usize) -> impl Iterator<Item=usize> + '_ {
+    let p = self.point(i);
+    NEIGHBORS.iter().filter_map(move |n| self.index(&(p + *n)))
+  }
 
-    for n in get_3d_neighbors(&current) {
-      if visited.contains(&n) {
+  pub fn lava_volume(&self) -> u64 {
+    self.bits.iter().map(|word| word.count_ones() as u64).sum()
+  }
+
+  // Labels every cube that matches `lava` with a component number starting
+  // from 1, 0 is left for the cubes of the other material. Components are
+  // numbered in the order their first cube appears in the grid, so for air
+  // component 1 is always the outside
+  pub fn label_components(&self, lava: bool) -> (Vec<u32>, Vec<Component>) {
+    let mut labels = vec![0u32; self.len()];
+    let mut components = vec![];
+    let mut to_visit = vec![];
+
+    for start in 0..self.len() {
+      if labels[start] != 0 || self.is_lava_index(start) != lava {
         continue;
       }
 
-      let curr_value = cleared_grid.get(&n);
-      if curr_value.is_none() {
-        continue
-      }
-      if curr_value.unwrap() != &'#' {
-        cleared_grid.insert(n.clone(), ' ');
-        to_visit.push_back(n);
+      let label = components.len() as u32 + 1;
+      let start_point = self.point(start);
+      let mut component = Component { volume: 0, min: start_point, max: start_point, surface_area: 0 };
+      labels[start] = label;
+      to_visit.push(start);
+
+      while let Some(current) = to_visit.pop() {
+        let p = self.point(current);
+        component.volume += 1;
+        component.min = Point3 { x: component.min.x.min(p.x), y: component.min.y.min(p.y), z: component.min.z.min(p.z) };
+        component.max = Point3 { x: component.max.x.max(p.x), y: component.max.y.max(p.y), z: component.max.z.max(p.z) };
+
+        for n in self.neighbors(current) {
+          if self.is_lava_index(n) != lava {
+            component.surface_area += 1;
+          } else if labels[n] == 0 {
+            labels[n] = label;
+            to_visit.push(n);
+          }
+        }
       }
-
-      visited.insert(current);
+      components.push(component);
     }
+
+    (labels, components)
   }
 
-  cleared_grid
-}
+  pub fn exterior_surface_area(&self) -> u64 {
+    self.air[0].surface_area
+  }
 
-fn fill_grid_with_points(points: &Vec<Point3>, empty_fill_grid: &HashMap<Point3, char>) -> HashMap<Point3, char> {
-  let mut filled_grid = empty_fill_grid.clone();
-  points
-    .iter()
-    .for_each(|p| {
-      filled_grid.insert(*p, '#');
-    });
-
-  filled_grid
-}
-
-fn fill_grid_with_surrounding_cube(points: &Vec<Point3>) -> HashMap<Point3, char> {
-  let min_x = points.iter().map(|p| p.x).min().unwrap() - 1;
-  let max_x = points.iter().map(|p| p.x).max().unwrap() + 1;
-  let min_y = points.iter().map(|p| p.y).min().unwrap() - 1;
-  let max_y = points.iter().map(|p| p.y).max().unwrap() + 1;
-  let min_z = points.iter().map(|p| p.z).min().unwrap() - 1;
-  let max_z = points.iter().map(|p| p.z).max().unwrap() + 1;
-
-  let mut grid = HashMap::new();
-  for x in min_x..=max_x {
-    for y in min_y..=max_y {
-      for z in min_z..=max_z {
-        grid.insert(Point3 {x,y,z}, '.');
+  // Lava faces that touch the outside air, as the cube and the index of the
+  // direction (from `NEIGHBORS`) the face points in
+  pub fn exterior_faces(&self) -> Vec<(Point3, usize)> {
+    let mut faces = vec![];
+
+    for i in (0..self.len()).filter(|i| self.is_lava_index(*i)) {
+      let p = self.point(i);
+      for (d, n) in NEIGHBORS.iter().enumerate() {
+        if self.index(&(p + *n)).is_some_and(|neighbor| self.air_labels[neighbor] == 1) {
+          faces.push((p, d));
+        }
       }
     }
+
+    faces
   }
 
-  grid
+  pub fn analyze(&self) -> VoxelReport {
+    let (_, droplets) = self.label_components(true);
+    let exterior = self.air[0];
+    let air = self.air[1..].to_vec();
+
+    let lava_volume = self.lava_volume();
+    let surface_area: u64 = droplets.iter().map(|d| d.surface_area).sum();
+    VoxelReport {
+      lava_volume,
+      enclosed_volume: lava_volume + air.iter().map(|a| a.volume).sum::<u64>(),
+      surface_area,
+      exterior_surface_area: exterior.surface_area,
+      interior_surface_area: surface_area - exterior.surface_area,
+      droplets,
+      air_pockets: air,
+    }
+  }
 }
 
-
-fn parse_input(lines: &Vec<String>) -> Vec<Point3> {
+pub fn parse_input(lines: &[String]) -> Vec<Point3> {
   lines
     .iter()
     .map(|l| l.split(","))
@@ -129,10 +223,10 @@ fn split_to_point3(split: Split<&str>) -> Point3 {
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-struct Point3 {
-  x: i64,
-  y: i64,
-  z: i64,
+pub struct Point3 {
+  pub(crate) x: i64,
+  pub(crate) y: i64,
+  pub(crate) z: i64,
 }
 
 impl Add for Point3 {
@@ -165,28 +259,23 @@ fn find_surface_area(points: &Vec<Point3>) -> u64 {
   surface_area
 }
 
-fn get_3d_neighbors(point: &Point3) -> Vec<Point3> {
-  get_3d_neighbors_modifiers()
-    .iter()
-    .map(|n| *point + *n)
-    .collect()
-}
-
-fn get_3d_neighbors_modifiers() -> Vec<Point3> {
-  vec![
-    Point3 { x: -1, y: 0, z: 0 },
-    Point3 { x: 1, y: 0, z: 0 },
-    Point3 { x: 0, y: -1, z: 0 },
-    Point3 { x: 0, y: 1, z: 0 },
-    Point3 { x: 0, y: 0, z: -1 },
-    Point3 { x: 0, y: 0, z: 1 },
-  ]
+pub const NEIGHBORS: [Point3; 6] = [
+  Point3 { x: -1, y: 0, z: 0 },
+  Point3 { x: 1, y: 0, z: 0 },
+  Point3 { x: 0, y: -1, z: 0 },
+  Point3 { x: 0, y: 1, z: 0 },
+  Point3 { x: 0, y: 0, z: -1 },
+  Point3 { x: 0, y: 0, z: 1 },
+];
+
+pub fn get_3d_neighbors_modifiers() -> Vec<Point3> {
+  NEIGHBORS.to_vec()
 }
 
 
 #[cfg(test)]
 mod tests {
-  use crate::day18::{find_surface_area, parse_input, solve_part_2};
+  use crate::day18::{find_surface_area, parse_input, Point3, solve_part_2, VoxelGrid};
 
   #[test]
   fn test_simple_input() {
@@ -212,6 +301,52 @@ mod tests {
     assert_eq!(solve_part_2(&points), 58);
   }
 
+  #[test]
+  fn test_voxel_report() {
+    let input = get_part_1_sample_input();
+    let points = parse_input(&input);
+    let report = VoxelGrid::new(&points).analyze();
+
+    assert_eq!(report.lava_volume, 13);
+    assert_eq!(report.enclosed_volume, 14);
+    assert_eq!(report.surface_area, 64);
+    assert_eq!(report.exterior_surface_area, 58);
+    assert_eq!(report.interior_surface_area, 6);
+    assert_eq!(report.droplets.len(), 6);
+    assert_eq!(report.droplets.iter().map(|d| d.volume).sum::<u64>(), 13);
+
+    assert_eq!(report.air_pockets.len(), 1);
+    let pocket = report.air_pockets[0];
+    assert_eq!(pocket.volume, 1);
+    assert_eq!(pocket.min, Point3 { x: 2, y: 2, z: 5 });
+    assert_eq!(pocket.max, Point3 { x: 2, y: 2, z: 5 });
+    assert_eq!(pocket.surface_area, 6);
+  }
+
+  #[test]
+  fn test_hollow_cube() {
+    // 5x5x5 shell around a 3x3x3 pocket
+    let mut points = vec![];
+    for x in 0..5 {
+      for y in 0..5 {
+        for z in 0..5 {
+          if [x, y, z].iter().any(|v| *v == 0 || *v == 4) {
+            points.push(Point3 { x, y, z });
+          }
+        }
+      }
+    }
+    let report = VoxelGrid::new(&points).analyze();
+    assert_eq!(report.lava_volume, 125 - 27);
+    assert_eq!(report.enclosed_volume, 125);
+    assert_eq!(report.exterior_surface_area, 6 * 25);
+    assert_eq!(report.interior_surface_area, 6 * 9);
+    assert_eq!(report.air_pockets[0].volume, 27);
+    assert_eq!(report.air_pockets[0].min, Point3 { x: 1, y: 1, z: 1 });
+    assert_eq!(report.air_pockets[0].max, Point3 { x: 3, y: 3, z: 3 });
+    assert_eq!(report.droplets.len(), 1);
+  }
+
   fn get_simple_input() -> Vec<String> {
     vec![
       "1,1,1".to_string(),
@@ -0,0 +1,221 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::day18::{Point3, VoxelGrid, NEIGHBORS};
+
+// One rectangular face of the mesh. Corners go anticlockwise when looking at
+// the face from the outside, which is the side `normal` points to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quad {
+  pub corners: [[i64; 3]; 4],
+  pub normal: [i64; 3],
+}
+
+impl Quad {
+  pub fn area(&self) -> i64 {
+    let size: Vec<i64> = (0..3)
+      .map(|axis| self.corners.iter().map(|c| c[axis]).max().unwrap() - self.corners.iter().map(|c| c[axis]).min().unwrap())
+      .filter(|length| *length != 0)
+      .collect();
+    size.iter().product()
+  }
+}
+
+fn coordinate(p: &Point3, axis: usize) -> i64 {
+  match axis {
+    0 => p.x,
+    1 => p.y,
+    _ => p.z,
+  }
+}
+
+// Quads for every face of the droplet that can be seen from outside. With
+// `merge` the faces in each plane are greedily combined into rectangles that
+// are as wide, then as tall, as possible
+pub fn exterior_quads(grid: &VoxelGrid, merge: bool) -> Vec<Quad> {
+  // Faces grouped by direction and the plane they're in, as 2d cells in that
+  // plane. The two in-plane axes follow on cyclically from the normal's axis so
+  // (u, v, normal) is always right handed
+  let mut planes: HashMap<(usize, i64), BTreeSet<(i64, i64)>> = HashMap::new();
+  for (cube, direction) in grid.exterior_faces() {
+    let axis = direction / 2;
+    let cell = (coordinate(&cube, (axis + 2) % 3), coordinate(&cube, (axis + 1) % 3));
+    planes.entry((direction, coordinate(&cube, axis))).or_default().insert(cell);
+  }
+
+  let mut keys: Vec<&(usize, i64)> = planes.keys().collect();
+  keys.sort();
+
+  let mut quads = vec![];
+  for key in keys {
+    let (direction, layer) = *key;
+    let axis = direction / 2;
+    let n = NEIGHBORS[direction];
+    let normal = [n.x, n.y, n.z];
+    let outward = normal[axis] > 0;
+    let plane = if outward { layer + 1 } else { layer };
+
+    for (v, u, width, height) in rectangles(&planes[key], merge) {
+      let corner = |u: i64, v: i64| {
+        let mut c = [0; 3];
+        c[axis] = plane;
+        c[(axis + 1) % 3] = u;
+        c[(axis + 2) % 3] = v;
+        c
+      };
+      let mut corners = [corner(u, v), corner(u + width, v), corner(u + width, v + height), corner(u, v + height)];
+      if !outward {
+        corners.reverse();
+      }
+      quads.push(Quad { corners, normal });
+    }
+  }
+
+  quads
+}
+
+// Covers the cells with (v, u, width, height) rectangles, one per cell when not
+// merging. Cells are (v, u) so the set iterates row by row
+fn rectangles(cells: &BTreeSet<(i64, i64)>, merge: bool) -> Vec<(i64, i64, i64, i64)> {
+  if !merge {
+    return cells.iter().map(|(v, u)| (*v, *u, 1, 1)).collect();
+  }
+
+  let mut remaining = cells.clone();
+  let mut rectangles = vec![];
+  while let Some(&(v, u)) = remaining.iter().next() {
+    let mut width = 1;
+    while remaining.contains(&(v, u + width)) {
+      width += 1;
+    }
+    let mut height = 1;
+    while (u..u + width).all(|x| remaining.contains(&(v + height, x))) {
+      height += 1;
+    }
+
+    for y in v..v + height {
+      for x in u..u + width {
+        remaining.remove(&(y, x));
+      }
+    }
+    rectangles.push((v, u, width, height));
+  }
+
+  rectangles
+}
+
+// Wavefront OBJ with shared vertices and one quad face per `Quad`
+pub fn to_obj(quads: &[Quad]) -> String {
+  let mut vertex_ids: HashMap<[i64; 3], usize> = HashMap::new();
+  let mut vertices = String::new();
+  let mut faces = String::new();
+
+  for quad in quads {
+    let ids: Vec<String> = quad
+      .corners
+      .iter()
+      .map(|c| {
+        let next_id = vertex_ids.len() + 1;
+        let id = *vertex_ids.entry(*c).or_insert_with(|| {
+          vertices.push_str(&format!("v {} {} {}\n", c[0], c[1], c[2]));
+          next_id
+        });
+        id.to_string()
+      })
+      .collect();
+    faces.push_str(&format!("f {}\n", ids.join(" ")));
+  }
+
+  format!("o droplet\n{}{}", vertices, faces)
+}
+
+// ASCII STL only has triangles, so every quad becomes two
+pub fn to_stl(quads: &[Quad]) -> String {
+  let mut stl = String::from("solid droplet\n");
+  for quad in quads {
+    for triangle in [[0, 1, 2], [0, 2, 3]] {
+      stl.push_str(&format!("  facet normal {} {} {}\n    outer loop\n", quad.normal[0], quad.normal[1], quad.normal[2]));
+      for corner in triangle {
+        let c = quad.corners[corner];
+        stl.push_str(&format!("      vertex {} {} {}\n", c[0], c[1], c[2]));
+      }
+      stl.push_str("    endloop\n  endfacet\n");
+    }
+  }
+  stl.push_str("endsolid droplet\n");
+  stl
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::day18::{parse_input, Point3, VoxelGrid};
+  use crate::day18_mesh::{exterior_quads, Quad, to_obj, to_stl};
+
+  // Corners anticlockwise from outside means the cross product of the first
+  // two edges points along the normal
+  fn winding_matches_normal(quad: &Quad) -> bool {
+    let [a, b, c, _] = quad.corners;
+    let e1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let e2 = [c[0] - b[0], c[1] - b[1], c[2] - b[2]];
+    let cross = [
+      e1[1] * e2[2] - e1[2] * e2[1],
+      e1[2] * e2[0] - e1[0] * e2[2],
+      e1[0] * e2[1] - e1[1] * e2[0],
+    ];
+    (0..3).all(|i| cross[i].signum() == quad.normal[i])
+  }
+
+  #[test]
+  fn test_single_cube() {
+    let grid = VoxelGrid::new(&[Point3 { x: 0, y: 0, z: 0 }]);
+    let quads = exterior_quads(&grid, false);
+    assert_eq!(quads.len(), 6);
+    assert!(quads.iter().all(winding_matches_normal));
+
+    let obj = to_obj(&quads);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+
+    let stl = to_stl(&quads);
+    assert!(stl.starts_with("solid droplet\n"));
+    assert_eq!(stl.matches("facet normal").count(), 12);
+    assert!(stl.contains("facet normal 0 0 -1"));
+  }
+
+  #[test]
+  fn test_sample_exterior_only() {
+    let grid = VoxelGrid::new(&parse_input(&get_sample_input()));
+    let quads = exterior_quads(&grid, false);
+    assert_eq!(quads.len(), 58);
+    assert!(quads.iter().all(winding_matches_normal));
+
+    let merged = exterior_quads(&grid, true);
+    assert!(merged.len() < quads.len());
+    assert_eq!(merged.iter().map(|q| q.area()).sum::<i64>(), 58);
+    assert!(merged.iter().all(winding_matches_normal));
+  }
+
+  #[test]
+  fn test_merge_hollow_cube() {
+    let mut points = vec![];
+    for x in 0..5 {
+      for y in 0..5 {
+        for z in 0..5 {
+          if [x, y, z].iter().any(|v| *v == 0 || *v == 4) {
+            points.push(Point3 { x, y, z });
+          }
+        }
+      }
+    }
+    let quads = exterior_quads(&VoxelGrid::new(&points), true);
+    assert_eq!(quads.len(), 6);
+    assert!(quads.iter().all(|q| q.area() == 25));
+    assert_eq!(to_obj(&quads).lines().filter(|l| l.starts_with("v ")).count(), 8);
+  }
+
+  fn get_sample_input() -> Vec<String> {
+    [
+      "2,2,2", "1,2,2", "3,2,2", "2,1,2", "2,3,2", "2,2,1", "2,2,3",
+      "2,2,4", "2,2,6", "1,2,5", "3,2,5", "2,1,5", "2,3,5",
+    ].iter().map(|l| l.to_string()).collect()
+  }
+}
@@ -1,5 +1,9 @@
-use std::cmp::{max, Ordering};
-use std::collections::{BinaryHeap, HashSet};
+use std::cmp::max;
+use std::collections::HashSet;
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
 
 use crate::utils::read_chunks;
 
@@ -37,343 +41,437 @@ fn solve_two(blueprints: &Vec<Blueprint>) -> u64 {
 }
 
 fn parse_input(lines: &Vec<String>) -> Vec<Blueprint> {
-  // Blueprint 1:
-  // Each ore robot costs 2 ore.
-  // Each clay robot costs 4 ore.
-  // Each obsidian robot costs 2 ore and 16 clay.
-  // Each geode robot costs 2 ore and 9 obsidian.
-  lines
-    .iter()
-    .map(|s| s.replace(":", ""))
-    .map(|s| s.replace(".", ""))
-    .map(|s| s.split(" ").map(|p| p.to_string()).collect::<Vec<String>>())
-    .map(|s| str_to_blueprint(&s))
-    .map(|b| fill_in_max_required(&b))
-    .collect()
+  parse_blueprints(lines).unwrap_or_else(|e| panic!("Invalid blueprint on {}", e))
 }
 
-fn fill_in_max_required(blueprint: &Blueprint) -> Blueprint {
-  Blueprint {
-    id: blueprint.id,
-    ore_robot_cost: blueprint.ore_robot_cost.clone(),
-    clay_robot_cost: blueprint.clay_robot_cost.clone(),
-    obsidian_robot_cost: blueprint.obsidian_robot_cost.clone(),
-    geode_robot_cost: blueprint.geode_robot_cost.clone(),
-    max_robots_required: Quantity {
-      ore: max(
-        blueprint.ore_robot_cost.ore,
-        max(blueprint.clay_robot_cost.ore,
-            max(blueprint.obsidian_robot_cost.ore,
-                blueprint.geode_robot_cost.ore),
-        ),
-      ),
-      clay: max(
-        blueprint.ore_robot_cost.clay,
-        max(blueprint.clay_robot_cost.clay,
-            max(blueprint.obsidian_robot_cost.clay,
-                blueprint.geode_robot_cost.clay),
-        ),
-      ),
-      obsidian: max(
-        blueprint.ore_robot_cost.obsidian,
-        max(blueprint.obsidian_robot_cost.obsidian,
-            max(blueprint.obsidian_robot_cost.obsidian,
-                blueprint.geode_robot_cost.obsidian),
-        ),
-      ),
-      geode: 0,
-    },
-  }
+// Problem with one line of a blueprint list, `line` is 1-based
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlueprintParseError {
+  pub line: usize,
+  pub message: String,
 }
 
-fn str_to_blueprint(parts: &Vec<String>) -> Blueprint {
-  Blueprint {
-    id: parts.get(1).unwrap().parse().unwrap(),
-    ore_robot_cost: Quantity {
-      ore: parts.get(6).unwrap().parse().unwrap(),
-      clay: 0,
-      obsidian: 0,
-      geode: 0,
-    },
-    clay_robot_cost: Quantity {
-      ore: parts.get(12).unwrap().parse().unwrap(),
-      clay: 0,
-      obsidian: 0,
-      geode: 0,
-    },
-    obsidian_robot_cost: Quantity {
-      ore: parts.get(18).unwrap().parse().unwrap(),
-      clay: parts.get(21).unwrap().parse().unwrap(),
-      obsidian: 0,
-      geode: 0,
-    },
-    geode_robot_cost: Quantity {
-      ore: parts.get(27).unwrap().parse().unwrap(),
-      clay: 0,
-      obsidian: parts.get(30).unwrap().parse().unwrap(),
-      geode: 0,
-    },
-    max_robots_required: Quantity { ore: 0, clay: 0, obsidian: 0, geode: 0 },
+impl fmt::Display for BlueprintParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
   }
 }
 
-#[derive(Clone, Hash)]
-struct State {
-  max_required_robots: Quantity,
-  minute: usize,
-  material: Quantity,
-  robots: Quantity,
+// A set of robots, one per resource, each of which collects one of its resource
+// a minute. Resources are numbered in the order their robots are described,
+// and `costs[robot][resource]` is how much of a resource a robot needs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Blueprint {
+  pub id: usize,
+  pub resources: Vec<String>,
+  costs: Vec<Vec<usize>>,
 }
 
-impl State {
-  fn score(&self) -> i64 {
-    self.minute as i64
+impl Blueprint {
+  pub fn resource_index(&self, name: &str) -> Option<usize> {
+    self.resources.iter().position(|r| r == name)
   }
+}
 
-  fn to_string(&self) -> String {
-    format!("{} {} {}", self.minute, self.robots.to_string(), self.material.to_string())
+// Parses lines in the puzzle's format, with any resource names and any number
+// of robots:
+//   Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. ...
+// Every resource a robot costs has to have a robot of its own somewhere in the
+// blueprint, otherwise it could never be collected
+pub fn parse_blueprints(lines: &[String]) -> Result<Vec<Blueprint>, BlueprintParseError> {
+  lazy_static! {
+    static ref RE_BLUEPRINT: Regex = Regex::new(r"^Blueprint (\d+):(.*)$").unwrap();
+    static ref RE_ROBOT: Regex = Regex::new(r"^Each (\w+) robot costs (.+)$").unwrap();
+    static ref RE_COST: Regex = Regex::new(r"^(\d+) (\w+)$").unwrap();
   }
-}
 
-impl Quantity {
-  fn to_string(&self) -> String {
-    format!("{} {} {} {}", self.ore, self.clay, self.obsidian, self.geode)
+  let mut blueprints = vec![];
+  for (i, line) in lines.iter().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let error = |message: String| BlueprintParseError { line: i + 1, message };
+
+    let captures = RE_BLUEPRINT
+      .captures(line.trim())
+      .ok_or_else(|| error("expected a line starting 'Blueprint <id>:'".to_string()))?;
+    let id = captures[1].parse().map_err(|_| error(format!("invalid blueprint id '{}'", &captures[1])))?;
+
+    // Robot names in order along with their costs by resource name
+    let mut robots: Vec<(String, Vec<(String, usize)>)> = vec![];
+    for sentence in captures[2].split('.').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+      let robot = RE_ROBOT
+        .captures(sentence)
+        .ok_or_else(|| error(format!("expected 'Each <resource> robot costs ...' but found '{}'", sentence)))?;
+      if robots.iter().any(|(name, _)| name == &robot[1]) {
+        return Err(error(format!("{} robot is described twice", &robot[1])));
+      }
+
+      let mut costs = vec![];
+      for part in robot[2].split(" and ") {
+        let cost = RE_COST
+          .captures(part.trim())
+          .ok_or_else(|| error(format!("expected a cost like '4 ore' but found '{}'", part.trim())))?;
+        let amount = cost[1].parse().map_err(|_| error(format!("invalid amount '{}'", &cost[1])))?;
+        costs.push((cost[2].to_string(), amount));
+      }
+      robots.push((robot[1].to_string(), costs));
+    }
+    if robots.is_empty() {
+      return Err(error("a blueprint needs at least one robot".to_string()));
+    }
+
+    let resources: Vec<String> = robots.iter().map(|(name, _)| name.clone()).collect();
+    let mut costs = vec![vec![0; resources.len()]; resources.len()];
+    for (robot, (name, robot_costs)) in robots.iter().enumerate() {
+      for (resource, amount) in robot_costs {
+        let index = resources
+          .iter()
+          .position(|r| r == resource)
+          .ok_or_else(|| error(format!("{} robot costs {} but nothing collects it", name, resource)))?;
+        costs[robot][index] += amount;
+      }
+    }
+
+    blueprints.push(Blueprint { id, resources, costs });
   }
+
+  Ok(blueprints)
 }
 
-impl Ord for State {
-  fn cmp(&self, other: &Self) -> Ordering {
-    self.score().cmp(&other.score())
-  }
+// Search over which robot to build next rather than what to do each minute.
+// Waiting is only ever useful to save up for a robot, so every branch skips
+// straight to the minute that robot gets built
+struct Search<'a> {
+  blueprint: &'a Blueprint,
+  target: usize,
+  total_minutes: usize,
+  // The most of each resource that can be spent in one minute, so the most
+  // robots of it worth having
+  max_needed: Vec<usize>,
+  best: usize,
+  // Bits for each field of a state's key, or `None` when they don't all fit
+  // and states can't be told apart cheaply
+  key_widths: Option<Vec<u32>>,
+  seen: HashSet<(u128, u64)>,
+  // (minute, robot) for each robot built on the way to the current state
+  builds: Vec<(usize, usize)>,
+  best_builds: Vec<(usize, usize)>,
+  stats: SearchStats,
 }
 
-impl PartialOrd for State {
-  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-    Some(self.cmp(other))
-  }
+// How much work the search did. `pruned` counts states cut off by the upper
+// bound, `duplicates` ones that had already been explored by another route
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchStats {
+  pub states_explored: usize,
+  pub pruned: usize,
+  pub duplicates: usize,
 }
 
-impl PartialEq for State {
-  fn eq(&self, other: &Self) -> bool {
-    self.minute == other.minute &&
-      self.material == other.material &&
-      self.robots == other.robots
+#[derive(Clone)]
+struct SearchState {
+  minutes_left: usize,
+  robots: Vec<usize>,
+  material: Vec<usize>,
+}
+
+impl SearchState {
+  // Everything that tells two states apart. The minutes, robots and capped
+  // materials are packed together using `widths` bits each, in that order. The
+  // target material isn't capped, so it gets a whole field of its own
+  fn key(&self, target: usize, widths: &[u32]) -> (u128, u64) {
+    let capped = self.material.iter().enumerate().filter(|(r, _)| *r != target).map(|(_, m)| m);
+    let packed = std::iter::once(&self.minutes_left)
+      .chain(&self.robots)
+      .chain(capped)
+      .zip(widths)
+      .fold(0u128, |key, (value, width)| key << width | *value as u128);
+    (packed, self.material[target] as u64)
+  }
+
+  // What we'll end up with if nothing else gets built
+  fn collected(&self, resource: usize) -> usize {
+    self.material[resource] + self.robots[resource] * self.minutes_left
   }
 }
 
-impl Eq for State {}
+impl Search<'_> {
+  fn run(&mut self, state: SearchState) {
+    let collected = state.collected(self.target);
+    if collected > self.best {
+      self.best = collected;
+      self.best_builds = self.builds.clone();
+    }
+    if state.minutes_left <= 1 {
+      return;
+    }
+    if self.upper_bound(&state) <= self.best {
+      self.stats.pruned += 1;
+      return;
+    }
+    if let Some(widths) = &self.key_widths {
+      if !self.seen.insert(state.key(self.target, widths)) {
+        self.stats.duplicates += 1;
+        return;
+      }
+    }
+    self.stats.states_explored += 1;
+
+    // Target robots first, they're the most likely to raise `best` early and
+    // make the bound prune more
+    let (blueprint, target) = (self.blueprint, self.target);
+    let order = std::iter::once(target).chain((0..blueprint.resources.len()).rev().filter(|r| *r != target));
+    for robot in order {
+      // There's no point having more robots of a kind than can be spent in
+      // a single minute
+      if robot != self.target && state.robots[robot] >= self.max_needed[robot] {
+        continue;
+      }
+
+      let cost = &blueprint.costs[robot];
+      let wait = match minutes_until_affordable(cost, &state) {
+        Some(wait) => wait,
+        None => continue,
+      };
+      // Need at least a minute left after building for the robot to do anything
+      if wait + 1 >= state.minutes_left {
+        continue;
+      }
 
+      let minutes_left = state.minutes_left - wait - 1;
+      let mut next = SearchState { minutes_left, ..state.clone() };
+      for (resource, cost) in cost.iter().enumerate() {
+        next.material[resource] = state.material[resource] + state.robots[resource] * (wait + 1) - cost;
+      }
+      next.robots[robot] += 1;
 
-fn max_geodes_possible(blueprint: &Blueprint, total_minutes: usize) -> usize {
-  let mut final_states: Vec<State> = Vec::new();
-  let mut next_states: BinaryHeap<State> = BinaryHeap::new();
-  let mut state_seen: HashSet<String> = HashSet::new();
-
-
-  next_states.push(State {
-    max_required_robots: blueprint.max_robots_required.clone(),
-    minute: 0,
-    material: Quantity { ore: 0, clay: 0, obsidian: 0, geode: 0 },
-    robots: Quantity { ore: 1, clay: 0, obsidian: 0, geode: 0 },
-  });
-
-  while !next_states.is_empty() {
-    let state = next_states.pop().unwrap();
-    state_seen.insert(state.to_string());
-
-    if state.minute == total_minutes {
-      final_states.push(state);
-    } else {
-      let states = step(blueprint, &state);
-      for state in states {
-        if !state_seen.contains(&state.to_string())
-        {
-          next_states.push(state);
+      // Anything beyond what could possibly be spent before time runs out is
+      // the same as having exactly that much, which lets more states match
+      for (resource, material) in next.material.iter_mut().enumerate() {
+        if resource != self.target {
+          *material = (*material).min(self.max_needed[resource] * minutes_left);
         }
       }
+
+      self.builds.push((self.total_minutes - minutes_left, robot));
+      self.run(next);
+      self.builds.pop();
     }
   }
 
-  let max_geodes_cracked = final_states
-    .iter()
-    .map(|s| s.material.geode)
-    .max().unwrap();
+  // Most of the target this state could end with if every kind of robot had
+  // its own copy of the material to pay from, and one of each kind could be
+  // built every minute. Building each kind as soon as its copy allows means it
+  // is never built later than it could be for real, so this can only ever do
+  // better than the real thing and is safe to prune on
+  fn upper_bound(&self, state: &SearchState) -> usize {
+    let costs = &self.blueprint.costs;
+    let mut robots = state.robots.clone();
+    let mut pools = vec![state.material.clone(); robots.len()];
+    // Spending the target is ignored, so it only ever goes up
+    let mut collected = state.material[self.target];
+
+    for _ in 0..state.minutes_left {
+      let built: Vec<bool> = pools
+        .iter()
+        .zip(costs)
+        .map(|(pool, cost)| pool.iter().zip(cost).all(|(have, need)| have >= need))
+        .collect();
+
+      for (robot, pool) in pools.iter_mut().enumerate() {
+        for (resource, material) in pool.iter_mut().enumerate() {
+          if built[robot] {
+            *material -= costs[robot][resource];
+          }
+          *material += robots[resource];
+        }
+      }
+      collected += robots[self.target];
+      for (robot, built) in built.iter().enumerate() {
+        if *built {
+          robots[robot] += 1;
+        }
+      }
+    }
 
-  max_geodes_cracked
+    collected
+  }
 }
 
-fn step(blueprint: &Blueprint, state: &State) -> Vec<State> {
-  let mut next_states = Vec::new();
-  let mut built_robots = 0;
-  let mut built_geode_robot = false;
-  // If I can afford a geode robot, make one
-  if state.material.ore >= blueprint.geode_robot_cost.ore
-    && state.material.obsidian >= blueprint.geode_robot_cost.obsidian {
-    built_geode_robot = true;
-    let s = State {
-      max_required_robots: blueprint.max_robots_required.clone(),
-      minute: state.minute + 1,
-      material: Quantity {
-        ore: (state.material.ore - blueprint.geode_robot_cost.ore) + state.robots.ore,
-        clay: state.material.clay + state.robots.clay,
-        obsidian: (state.material.obsidian - blueprint.geode_robot_cost.obsidian) + state.robots.obsidian,
-        geode: state.material.geode + state.robots.geode,
-      },
-      robots: Quantity {
-        ore: state.robots.ore,
-        clay: state.robots.clay,
-        obsidian: state.robots.obsidian,
-        geode: state.robots.geode + 1,
-      },
-    };
-    next_states.push(s);
-  } else {
-    // If I can afford an obsidian robot, make one
-    let max_req_obsidian =
-      max(
-        blueprint.ore_robot_cost.obsidian,
-        max(blueprint.obsidian_robot_cost.obsidian,
-            max(blueprint.obsidian_robot_cost.obsidian,
-                blueprint.geode_robot_cost.obsidian),
-        ),
-      );
-    if state.material.ore >= blueprint.obsidian_robot_cost.ore &&
-      state.material.clay >= blueprint.obsidian_robot_cost.clay &&
-      state.robots.obsidian < max_req_obsidian {
-      built_robots += 1;
-      let s = State {
-        max_required_robots: blueprint.max_robots_required.clone(),
-        minute: state.minute + 1,
-        material: Quantity {
-          ore: (state.material.ore - blueprint.obsidian_robot_cost.ore) + state.robots.ore,
-          clay: (state.material.clay - blueprint.obsidian_robot_cost.clay) + state.robots.clay,
-          obsidian: state.material.obsidian + state.robots.obsidian,
-          geode: state.material.geode + state.robots.geode,
-        },
-        robots: Quantity {
-          ore: state.robots.ore,
-          clay: state.robots.clay,
-          obsidian: state.robots.obsidian + 1,
-          geode: state.robots.geode,
-        },
-      };
-      next_states.push(s);
-    }
-    // If I can afford a clay robot, make one
-    let max_req_clay =
-      max(
-        blueprint.ore_robot_cost.clay,
-        max(blueprint.clay_robot_cost.clay,
-            max(blueprint.obsidian_robot_cost.clay,
-                blueprint.geode_robot_cost.clay),
-        ),
-      );
-    if state.material.ore >= blueprint.clay_robot_cost.ore && state.robots.clay < max_req_clay {
-      built_robots += 1;
-      let s = State {
-        max_required_robots: blueprint.max_robots_required.clone(),
-        minute: state.minute + 1,
-        material: Quantity {
-          ore: (state.material.ore - blueprint.clay_robot_cost.ore) + state.robots.ore,
-          clay: state.material.clay + state.robots.clay,
-          obsidian: state.material.obsidian + state.robots.obsidian,
-          geode: state.material.geode + state.robots.geode,
-        },
-        robots: Quantity {
-          ore: state.robots.ore,
-          clay: state.robots.clay + 1,
-          obsidian: state.robots.obsidian,
-          geode: state.robots.geode,
-        },
-      };
-      next_states.push(s);
+// Minutes of collecting before there's enough to build the robot, or `None`
+// when nothing is collecting one of the resources it needs
+fn minutes_until_affordable(cost: &[usize], state: &SearchState) -> Option<usize> {
+  let mut wait = 0;
+  for ((cost, material), robots) in cost.iter().zip(&state.material).zip(&state.robots) {
+    if cost > material {
+      if *robots == 0 {
+        return None;
+      }
+      wait = max(wait, (cost - material).div_ceil(*robots));
     }
+  }
+  Some(wait)
+}
 
-    // If I can afford an ore robot, make one
-    let max_req_ore =
-      max(
-        blueprint.ore_robot_cost.ore,
-        max(blueprint.clay_robot_cost.ore,
-            max(blueprint.obsidian_robot_cost.ore,
-                blueprint.geode_robot_cost.ore),
-        ),
-      );
-    if state.material.ore >= blueprint.ore_robot_cost.ore && state.robots.ore < max_req_ore {
-      built_robots += 1;
-      let s = State {
-        max_required_robots: blueprint.max_robots_required.clone(),
-        minute: state.minute + 1,
-        material: Quantity {
-          ore: (state.material.ore - blueprint.ore_robot_cost.ore) + state.robots.ore,
-          clay: state.material.clay + state.robots.clay,
-          obsidian: state.material.obsidian + state.robots.obsidian,
-          geode: state.material.geode + state.robots.geode,
-        },
-        robots: Quantity {
-          ore: state.robots.ore + 1,
-          clay: state.robots.clay,
-          obsidian: state.robots.obsidian,
-          geode: state.robots.geode,
-        },
-      };
-      next_states.push(s);
+// Runs the search from a single robot collecting the first resource, returning
+// the most of `target` along with the (minute, robot) builds that get it
+fn search(blueprint: &Blueprint, target: usize, total_minutes: usize) -> (usize, Vec<(usize, usize)>, SearchStats) {
+  let count = blueprint.resources.len();
+  let max_needed: Vec<usize> = (0..count)
+    .map(|resource| blueprint.costs.iter().map(|cost| cost[resource]).max().unwrap_or(0))
+    .collect();
+
+  // The largest each part of a key can get. Robots other than the target stop
+  // at `max_needed`, apart from the one we start with, and their material gets
+  // capped at what could be spent in the time left
+  let bits = |most: usize| usize::BITS - most.leading_zeros();
+  let mut key_widths = vec![bits(total_minutes)];
+  key_widths.extend((0..count).map(|r| bits(if r == target { total_minutes + 1 } else { max_needed[r].max(1) })));
+  key_widths.extend((0..count).filter(|r| *r != target).map(|r| bits(max_needed[r] * total_minutes)));
+  let key_widths = Some(key_widths).filter(|widths| widths.iter().sum::<u32>() <= u128::BITS);
+
+  let mut robots = vec![0; count];
+  robots[0] = 1;
+
+  let mut search = Search {
+    blueprint,
+    target,
+    total_minutes,
+    max_needed,
+    best: 0,
+    key_widths,
+    seen: HashSet::new(),
+    builds: vec![],
+    best_builds: vec![],
+    stats: SearchStats::default(),
+  };
+  search.run(SearchState { minutes_left: total_minutes, robots, material: vec![0; count] });
+
+  (search.best, search.best_builds, search.stats)
+}
+
+fn max_geodes_possible(blueprint: &Blueprint, total_minutes: usize) -> usize {
+  search(blueprint, geode_index(blueprint), total_minutes).0
+}
+
+fn geode_index(blueprint: &Blueprint) -> usize {
+  blueprint.resource_index("geode").expect("Blueprint doesn't have geodes")
+}
+
+// What happened in one minute of the best plan. `built` indexes into the
+// blueprint's resources, and the counts are as they stand at the end of the
+// minute
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanMinute {
+  pub minute: usize,
+  pub built: Option<usize>,
+  pub robots: Vec<usize>,
+  pub resources: Vec<usize>,
+}
+
+pub struct BlueprintPlan {
+  pub id: usize,
+  pub resources: Vec<String>,
+  pub target: usize,
+  pub max_collected: usize,
+  pub quality_level: usize,
+  pub minutes: Vec<PlanMinute>,
+  pub stats: SearchStats,
+}
+
+// Plans for every blueprint in the file, collecting as much of the `target`
+// resource as possible
+pub fn blueprint_plans(lines: &[String], target: &str, total_minutes: usize) -> Vec<BlueprintPlan> {
+  parse_blueprints(lines)
+    .unwrap_or_else(|e| panic!("Invalid blueprint on {}", e))
+    .iter()
+    .map(|b| {
+      let target = b
+        .resource_index(target)
+        .unwrap_or_else(|| panic!("Blueprint {} doesn't have {}", b.id, target));
+      plan_blueprint(b, target, total_minutes)
+    })
+    .collect()
+}
+
+// The best build order for a blueprint, replayed minute by minute
+fn plan_blueprint(blueprint: &Blueprint, target: usize, total_minutes: usize) -> BlueprintPlan {
+  let (max_collected, builds, stats) = search(blueprint, target, total_minutes);
+
+  let mut robots = vec![0; blueprint.resources.len()];
+  robots[0] = 1;
+  let mut resources = vec![0; robots.len()];
+  let mut minutes = vec![];
+  for minute in 1..=total_minutes {
+    let built = builds.iter().find(|(m, _)| *m == minute).map(|(_, robot)| *robot);
+    if let Some(robot) = built {
+      for (resource, cost) in blueprint.costs[robot].iter().enumerate() {
+        resources[resource] -= cost;
+      }
+    }
+    for (resource, count) in robots.iter().enumerate() {
+      resources[resource] += count;
+    }
+    if let Some(robot) = built {
+      robots[robot] += 1;
     }
+    minutes.push(PlanMinute { minute, built, robots: robots.clone(), resources: resources.clone() });
   }
 
+  BlueprintPlan {
+    id: blueprint.id,
+    resources: blueprint.resources.clone(),
+    target,
+    max_collected,
+    quality_level: blueprint.id * max_collected,
+    minutes,
+    stats,
+  }
+}
 
-  // Last option is to just collect ore
-  // if !built_any_robot {
-  if built_robots < 3 && !built_geode_robot {
-    let s = State {
-      max_required_robots: blueprint.max_robots_required.clone(),
-      minute: state.minute + 1,
-      material: Quantity {
-        ore: state.material.ore + state.robots.ore,
-        clay: state.material.clay + state.robots.clay,
-        obsidian: state.material.obsidian + state.robots.obsidian,
-        geode: state.material.geode + state.robots.geode,
-      },
-      robots: Quantity {
-        ore: state.robots.ore,
-        clay: state.robots.clay,
-        obsidian: state.robots.obsidian,
-        geode: state.robots.geode,
-      },
-    };
-    next_states.push(s);
+pub fn plans_to_table(plans: &[BlueprintPlan]) -> String {
+  let target = plans.first().map_or("geode", |p| &p.resources[p.target]);
+  let mut table = format!("{:>4} {:>8} {:>8} {:>10} {:>10} {:>10}\n", "id", target, "quality", "explored", "pruned", "duplicates");
+  for plan in plans {
+    table.push_str(&format!(
+      "{:>4} {:>8} {:>8} {:>10} {:>10} {:>10}\n",
+      plan.id, plan.max_collected, plan.quality_level, plan.stats.states_explored, plan.stats.pruned, plan.stats.duplicates
+    ));
   }
+  table
+}
 
+pub fn build_order_to_text(plan: &BlueprintPlan) -> String {
+  let robot_width = plan.resources.iter().map(|r| r.len()).max().unwrap_or(0) + 6;
+  let mut text = format!("{:>6} {:<width$}", "minute", "built", width = robot_width);
+  for resource in &plan.resources {
+    text.push_str(&format!(" {:>w$}", resource, w = resource.len().max(4)));
+  }
+  text.push('\n');
 
-  next_states
+  for m in &plan.minutes {
+    let built = m.built.map_or(String::new(), |r| format!("{} robot", plan.resources[r]));
+    text.push_str(&format!("{:>6} {:<width$}", m.minute, built, width = robot_width));
+    for (resource, amount) in plan.resources.iter().zip(&m.resources) {
+      text.push_str(&format!(" {:>w$}", amount, w = resource.len().max(4)));
+    }
+    text.push('\n');
+  }
+  text
 }
 
-
 fn find_quality_level_for_blueprint(blueprint: &Blueprint) -> usize {
   let geodes = max_geodes_possible(blueprint, 24);
   geodes * blueprint.id
 }
 
-#[derive(PartialEq, Eq, Clone, Hash)]
-struct Quantity {
-  ore: usize,
-  clay: usize,
-  obsidian: usize,
-  geode: usize,
-}
-
-#[derive(Clone)]
-struct Blueprint {
-  id: usize,
-  ore_robot_cost: Quantity,
-  clay_robot_cost: Quantity,
-  obsidian_robot_cost: Quantity,
-  geode_robot_cost: Quantity,
-  max_robots_required: Quantity,
-}
-
 #[cfg(test)]
 mod tests {
-  use crate::day19::{max_geodes_possible, parse_input, solve_one};
+  use crate::day19::{
+    build_order_to_text, max_geodes_possible, parse_blueprints, parse_input, plan_blueprint, plans_to_table, search,
+    solve_one, BlueprintParseError, SearchState,
+  };
 
   #[test]
   fn test_parsing_simple_input() {
@@ -383,20 +481,11 @@ mod tests {
     let bp2 = blueprints.get(1).unwrap();
 
     assert_eq!(bp1.id, 1);
-    assert_eq!(bp1.ore_robot_cost.ore, 4);
-    assert_eq!(bp1.clay_robot_cost.ore, 2);
-    assert_eq!(bp1.obsidian_robot_cost.ore, 3);
-    assert_eq!(bp1.obsidian_robot_cost.clay, 14);
-    assert_eq!(bp1.geode_robot_cost.ore, 2);
-    assert_eq!(bp1.geode_robot_cost.obsidian, 7);
+    assert_eq!(bp1.resources, vec!["ore", "clay", "obsidian", "geode"]);
+    assert_eq!(bp1.costs, vec![vec![4, 0, 0, 0], vec![2, 0, 0, 0], vec![3, 14, 0, 0], vec![2, 0, 7, 0]]);
 
     assert_eq!(bp2.id, 2);
-    assert_eq!(bp2.ore_robot_cost.ore, 2);
-    assert_eq!(bp2.clay_robot_cost.ore, 3);
-    assert_eq!(bp2.obsidian_robot_cost.ore, 3);
-    assert_eq!(bp2.obsidian_robot_cost.clay, 8);
-    assert_eq!(bp2.geode_robot_cost.ore, 3);
-    assert_eq!(bp2.geode_robot_cost.obsidian, 12);
+    assert_eq!(bp2.costs, vec![vec![2, 0, 0, 0], vec![3, 0, 0, 0], vec![3, 8, 0, 0], vec![3, 0, 12, 0]]);
   }
 
   #[test]
@@ -439,6 +528,96 @@ mod tests {
     assert_eq!(geodes, 62);
   }
 
+  #[test]
+  fn test_short_time_budgets() {
+    let input = get_part_1_input();
+    let blueprints = parse_input(&input);
+
+    let bp1 = blueprints.get(0).unwrap();
+    assert_eq!(max_geodes_possible(bp1, 0), 0);
+    assert_eq!(max_geodes_possible(bp1, 1), 0);
+    assert_eq!(max_geodes_possible(bp1, 18), 0);
+  }
+
+  #[test]
+  fn test_build_order_replays_to_max_geodes() {
+    let input = get_part_1_input();
+    let blueprints = parse_input(&input);
+
+    let plan = plan_blueprint(&blueprints[0], 3, 24);
+    assert_eq!(plan.max_collected, 9);
+    assert_eq!(plan.quality_level, 9);
+    assert_eq!(plan.minutes.len(), 24);
+    assert_eq!(plan.minutes[23].resources[3], 9);
+    assert!(plan.stats.states_explored > 0);
+
+    let geode_robots = plan.minutes.iter().filter(|m| m.built == Some(3)).count();
+    assert_eq!(plan.minutes[23].robots[3], geode_robots);
+
+    let text = build_order_to_text(&plan);
+    assert_eq!(text.lines().count(), 25);
+    assert!(text.contains("geode robot"));
+
+    let plan2 = plan_blueprint(&blueprints[1], 3, 24);
+    let table = plans_to_table(&[plan, plan2]);
+    assert_eq!(table.lines().count(), 3);
+    assert!(table.lines().next().unwrap().contains("geode"));
+    assert!(table.lines().nth(2).unwrap().starts_with("   2       12       24"));
+  }
+
+  #[test]
+  fn test_variant_economy() {
+    let lines = vec![
+      "Blueprint 7: Each wood robot costs 1 wood. Each plank robot costs 3 wood. Each chair robot costs 2 wood and 4 plank.".to_string(),
+    ];
+    let blueprints = parse_blueprints(&lines).unwrap();
+    let bp = &blueprints[0];
+    assert_eq!(bp.resources, vec!["wood", "plank", "chair"]);
+    assert_eq!(bp.costs[2], vec![2, 4, 0]);
+
+    // Two minutes of collecting wood, then a chair robot isn't affordable in
+    // time so the most chairs is zero but wood keeps growing
+    assert_eq!(search(bp, 2, 3).0, 0);
+    assert!(search(bp, 2, 12).0 > 0);
+    // With wood as the target the best plan is a wood robot in each of
+    // minutes 2 to 4, collecting 1 + 1 + 2 + 3 + 4 and spending 3
+    assert_eq!(search(bp, 0, 5).0, 8);
+  }
+
+  #[test]
+  fn test_blueprint_parse_errors() {
+    let lines: Vec<String> = [
+      "Blueprint 1: Each ore robot costs 4 ore.",
+      "",
+      "Blueprint 2: Each ore robot costs 4 gold.",
+      "Blueprint x: Each ore robot costs 4 ore.",
+      "Blueprint 3: Each ore robot costs four ore.",
+    ].iter().map(|l| l.to_string()).collect();
+
+    assert_eq!(parse_blueprints(&lines[0..1]).unwrap().len(), 1);
+    assert_eq!(
+      parse_blueprints(&lines[0..3]).unwrap_err(),
+      BlueprintParseError { line: 3, message: "ore robot costs gold but nothing collects it".to_string() }
+    );
+    assert_eq!(parse_blueprints(&lines[3..4]).unwrap_err().to_string(), "line 1: expected a line starting 'Blueprint <id>:'");
+    assert_eq!(
+      parse_blueprints(&lines[4..5]).unwrap_err().message,
+      "expected a cost like '4 ore' but found 'four ore'"
+    );
+  }
+
+  #[test]
+  fn test_state_keys_keep_large_material_apart() {
+    // Resource 1 is the target, so its material can be anything while
+    // resource 0's is capped to fit in a single bit
+    let state = |ore: usize, material: usize| SearchState { minutes_left: 3, robots: vec![1, 0], material: vec![ore, material] };
+    let widths = [2, 1, 1, 1];
+    assert_ne!(state(0, 0).key(1, &widths), state(0, 65_536).key(1, &widths));
+    assert_ne!(state(0, 1).key(1, &widths), state(0, 65_537).key(1, &widths));
+    assert_ne!(state(0, 1).key(1, &widths), state(1, 1).key(1, &widths));
+    assert_eq!(state(1, 65_537).key(1, &widths), state(1, 65_537).key(1, &widths));
+  }
+
   #[test]
   fn test_part_1() {
     let input = get_part_1_input();
@@ -1,81 +1,214 @@
-use std::collections::VecDeque;
+use std::fmt;
 
 use crate::utils::read_chunks;
 
 pub fn part_one() -> i64 {
   let lines = read_chunks("day20.txt", "\n");
   let numbers = parse_input(&lines);
-  let mixed_numbers = mix_numbers(&numbers, 1);
-  let grove_coords = find_grove_coords(&mixed_numbers);
-  grove_coords
+  mix(&numbers, 1, 1).grove_coordinates(&GROVE_OFFSETS)
 }
 
-#[derive(Clone)]
-struct Value {
-  number: i64,
-  position: usize,
+pub fn part_two() -> i64 {
+  let lines = read_chunks("day20.txt", "\n");
+  let numbers = parse_input(&lines);
+  mix(&numbers, 10, 811589153).grove_coordinates(&GROVE_OFFSETS)
 }
 
-fn mix_numbers(numbers: &Vec<i64>, times: usize) -> Vec<i64> {
-  let mut deque = numbers
-    .iter()
-    .enumerate()
-    .map(|(i, v)| Value { number: *v, position: i })
-    .collect::<VecDeque<Value>>();
-
-  for _ in 0..times {
-    for curr_position in 0..(numbers.len()) {
-      while deque.front().unwrap().position != curr_position {
-        deque.rotate_left(1);
-      }
+pub const GROVE_OFFSETS: [usize; 3] = [1000, 2000, 3000];
 
-      let num = deque.pop_front().unwrap().clone();
-      let rotations = num.number % (numbers.len() as i64 - 1);
+// The file being mixed, held as a blocked linked list of indexes into
+// `numbers`. Finding or moving a number means walking the blocks then
+// searching or shifting one of them, so each move is O(sqrt n) rather than
+// rotating through the whole file. Blocks only grow or shrink between rebuilds,
+// which is what keeps `block_of` correct
+pub struct MixingList {
+  numbers: Vec<i64>,
+  blocks: Vec<Vec<usize>>,
+  block_of: Vec<usize>,
+  block_size: usize,
+  moves_since_rebuild: usize,
+}
 
-      if num.number > 0 {
-        deque.rotate_left(rotations.abs() as usize);
-        deque.push_front(num.clone());
-      } else {
-        deque.rotate_right(rotations.abs() as usize);
-        deque.push_front(num.clone());
-      }
-    }
+// Multiplies every number by the decryption key then mixes the file `rounds`
+// times
+pub fn mix(numbers: &[i64], rounds: usize, key: i64) -> MixingList {
+  let mut list = MixingList::new(numbers.iter().map(|n| n * key).collect());
+  for _ in 0..rounds {
+    list.mix_round();
   }
+  list
+}
 
-  while deque.front().unwrap().number != 0 {
-    deque.rotate_left(1);
+// Undoes `rounds` of mixing. `mixed` is a mixed file as it came out of `mix`,
+// with `original_positions` saying where each of its numbers started out, and
+// the result is the file as it was before it was multiplied by `key`
+pub fn unmix(mixed: &[i64], original_positions: &[usize], rounds: usize, key: i64) -> Vec<i64> {
+  assert_eq!(mixed.len(), original_positions.len(), "Need an original position for every number");
+  assert_ne!(key, 0, "A decryption key of 0 can't be undone");
+  let mut numbers = vec![0; mixed.len()];
+  for (number, position) in mixed.iter().zip(original_positions) {
+    numbers[*position] = *number;
   }
 
-  deque
+  let mut list = MixingList::with_order(numbers, original_positions.to_vec());
+  for _ in 0..rounds {
+    list.unmix_round();
+  }
+  list
+    .order_from(0)
     .iter()
-    .map(|v| v.number)
+    .map(|i| {
+      let number = list.numbers[*i];
+      assert_eq!(number % key, 0, "{} isn't a multiple of the decryption key", number);
+      number / key
+    })
     .collect()
 }
 
-fn find_grove_coords(mixed_numbers: &Vec<i64>) -> i64 {
-  let zero_index = mixed_numbers.iter().position(|v| v == &0).unwrap();
-  let coord_one = mixed_numbers.get((zero_index + 1000) % mixed_numbers.len()).unwrap();
-  let coord_two = mixed_numbers.get((zero_index + 2000) % mixed_numbers.len()).unwrap();
-  let coord_three = mixed_numbers.get((zero_index + 3000) % mixed_numbers.len()).unwrap();
-  // println!("{} {} {} ", coord_one, coord_two, coord_three);
-  coord_one + coord_two + coord_three
+// Where a claimed mixed file first differs from what mixing really gives
+#[derive(Debug, Clone, PartialEq)]
+pub enum MixMismatch {
+  Length { expected: usize, found: usize },
+  NoZero,
+  Number { position: usize, expected: i64, found: i64 },
 }
 
+impl fmt::Display for MixMismatch {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MixMismatch::Length { expected, found } => write!(f, "expected {} numbers but found {}", expected, found),
+      MixMismatch::NoZero => write!(f, "the mixed file doesn't contain a 0"),
+      MixMismatch::Number { position, expected, found } => {
+        write!(f, "{} places after the 0 expected {} but found {}", position, expected, found)
+      }
+    }
+  }
+}
 
-pub fn part_two() -> i64 {
-  let lines = read_chunks("day20.txt", "\n");
-  let numbers = parse_input(&lines);
-  let decryption_key = 811589153;
-  let updated_numbers = numbers
-    .iter()
-    .map(|n| n * decryption_key)
-    .collect();
-  let mixed_numbers = mix_numbers(&updated_numbers, 10);
-  let grove_coords = find_grove_coords(&mixed_numbers);
-  grove_coords
+// Checks `claimed` is what you get from mixing `original`. The file is a
+// circle, so it can start anywhere as long as the order is right
+pub fn verify_mixed(original: &[i64], claimed: &[i64], rounds: usize, key: i64) -> Result<(), MixMismatch> {
+  if original.len() != claimed.len() {
+    return Err(MixMismatch::Length { expected: original.len(), found: claimed.len() });
+  }
+  let zero = claimed.iter().position(|n| *n == 0).ok_or(MixMismatch::NoZero)?;
+  let claimed = claimed[zero..].iter().chain(claimed[..zero].iter());
+
+  let expected = mix(original, rounds, key).values();
+  for (position, (expected, found)) in expected.iter().zip(claimed).enumerate() {
+    if expected != found {
+      return Err(MixMismatch::Number { position, expected: *expected, found: *found });
+    }
+  }
+  Ok(())
+}
+
+impl MixingList {
+  fn new(numbers: Vec<i64>) -> MixingList {
+    let order = (0..numbers.len()).collect();
+    MixingList::with_order(numbers, order)
+  }
+
+  // `order` is the original positions of the numbers as they currently are
+  fn with_order(numbers: Vec<i64>, order: Vec<usize>) -> MixingList {
+    let block_size = ((numbers.len() as f64).sqrt() as usize).max(1);
+    let mut list = MixingList {
+      blocks: vec![order],
+      block_of: vec![0; numbers.len()],
+      numbers,
+      block_size,
+      moves_since_rebuild: 0,
+    };
+    list.rebuild();
+    list
+  }
+
+  fn rebuild(&mut self) {
+    let order: Vec<usize> = self.blocks.concat();
+    self.blocks = order.chunks(self.block_size).map(|c| c.to_vec()).collect();
+    for (b, block) in self.blocks.iter().enumerate() {
+      for index in block {
+        self.block_of[*index] = b;
+      }
+    }
+    self.moves_since_rebuild = 0;
+  }
+
+  // Moves every number once, in the order they were originally in the file
+  fn mix_round(&mut self) {
+    for index in 0..self.numbers.len() {
+      self.move_by(index, self.numbers[index]);
+    }
+  }
+
+  // Moving a number back the same distance puts it back between the same two
+  // neighbours, so going through them in reverse undoes a round
+  fn unmix_round(&mut self) {
+    for index in (0..self.numbers.len()).rev() {
+      self.move_by(index, -self.numbers[index]);
+    }
+  }
+
+  // Moves the number originally at `index` forward `steps` places, wrapping
+  // around the file
+  fn move_by(&mut self, index: usize, steps: i64) {
+    let len = self.numbers.len() as i64;
+    if len < 2 {
+      return;
+    }
+
+    let block = self.block_of[index];
+    let offset = self.blocks[block].iter().position(|i| *i == index).unwrap();
+    let position: usize = self.blocks[..block].iter().map(|b| b.len()).sum::<usize>() + offset;
+    self.blocks[block].remove(offset);
+
+    // With the number taken out there are `len - 1` places it can go
+    let mut target = (position as i64 + steps).rem_euclid(len - 1) as usize;
+    for (b, block) in self.blocks.iter_mut().enumerate() {
+      if target <= block.len() {
+        block.insert(target, index);
+        self.block_of[index] = b;
+        break;
+      }
+      target -= block.len();
+    }
+
+    self.moves_since_rebuild += 1;
+    if self.moves_since_rebuild >= self.block_size {
+      self.rebuild();
+    }
+  }
+
+  // Original positions of the numbers going round the file from the one that
+  // started out at `start`
+  fn order_from(&self, start: usize) -> Vec<usize> {
+    let order: Vec<usize> = self.blocks.concat();
+    let first = order.iter().position(|i| *i == start).unwrap();
+    order[first..].iter().chain(order[..first].iter()).copied().collect()
+  }
+
+  fn zero_index(&self) -> usize {
+    self.numbers.iter().position(|n| *n == 0).expect("The file doesn't contain a 0")
+  }
+
+  // The mixed file, starting from the 0
+  pub fn values(&self) -> Vec<i64> {
+    self.order_from(self.zero_index()).iter().map(|i| self.numbers[*i]).collect()
+  }
+
+  // Where each number in `values` was in the original file
+  pub fn original_positions(&self) -> Vec<usize> {
+    self.order_from(self.zero_index())
+  }
+
+  // Sum of the numbers the given distances after the 0, wrapping around
+  pub fn grove_coordinates(&self, offsets: &[usize]) -> i64 {
+    let values = self.values();
+    offsets.iter().map(|o| values[o % values.len()]).sum()
+  }
 }
 
-fn parse_input(lines: &Vec<String>) -> Vec<i64> {
+pub fn parse_input(lines: &[String]) -> Vec<i64> {
   lines
     .iter()
     .map(|s| s.parse().unwrap())
@@ -85,30 +218,32 @@ fn parse_input(lines: &Vec<String>) -> Vec<i64> {
 
 #[cfg(test)]
 mod tests {
-  use crate::day20::{find_grove_coords, mix_numbers, parse_input};
+  use crate::day20::{mix, parse_input, unmix, verify_mixed, MixMismatch, GROVE_OFFSETS};
 
   #[test]
   fn test_mixing_example_1() {
     let input = get_part_1_input();
     let numbers = parse_input(&input);
-    let mixed_numbers = mix_numbers(&numbers, 1);
-    let grove_coords = find_grove_coords(&mixed_numbers);
+    let mixed = mix(&numbers, 1, 1);
+    let grove_coords = mixed.grove_coordinates(&GROVE_OFFSETS);
 
     assert_eq!(grove_coords, 3);
+    assert_eq!(mixed.grove_coordinates(&[1000]), 4);
+    assert_eq!(mixed.grove_coordinates(&[0, 1, 2, 7]), 0 + 3 + -2);
   }
 
   #[test]
   fn test_mixing_fake_input() {
     let input = get_part_1_fake_input();
     let numbers = parse_input(&input);
-    let mixed_numbers = mix_numbers(&numbers, 1);
+    let mixed = mix(&numbers, 1, 1);
 
     assert_eq!(
-      mixed_numbers,
+      mixed.values(),
       vec![0, -5, 9, 2]
     );
 
-    let grove_coords = find_grove_coords(&mixed_numbers);
+    let grove_coords = mixed.grove_coordinates(&GROVE_OFFSETS);
 
     assert_eq!(grove_coords, 0);
   }
@@ -116,7 +251,7 @@ mod tests {
   #[test]
   fn test_0_n1_n1_1() {
     let numbers = vec![0, -1, -1, 1];
-    let mixed = mix_numbers(&numbers, 1);
+    let mixed = mix(&numbers, 1, 1).values();
     assert_eq!(
       mixed,
       vec![0, -1, 1, -1]
@@ -126,7 +261,7 @@ mod tests {
   #[test]
   fn test_3_1_0() {
     let numbers = vec![3, 1, 0];
-    let mixed = mix_numbers(&numbers, 1);
+    let mixed = mix(&numbers, 1, 1).values();
     assert_eq!(
       mixed,
       vec![0, 3, 1]
@@ -136,8 +271,7 @@ mod tests {
   #[test]
   fn test_adding_8() {
     let numbers = vec![1, 2, -3, 3, -2, 0, 8];
-    let mixed_numbers = mix_numbers(&numbers, 1);
-    let grove_coords = find_grove_coords(&mixed_numbers);
+    let grove_coords = mix(&numbers, 1, 1).grove_coordinates(&GROVE_OFFSETS);
     assert_eq!(grove_coords, 7);
   }
 
@@ -146,18 +280,86 @@ mod tests {
     let numbers: Vec<i64> = vec![
       811589153, 1623178306, -2434767459, 2434767459, -1623178306, 0, 3246356612,
     ];
-    let mixed_numbers = mix_numbers(&numbers, 10);
+    let mixed = mix(&numbers, 10, 1);
     assert_eq!(
-      mixed_numbers,
+      mixed.values(),
       vec![
         0, -2434767459, 1623178306, 3246356612, -1623178306, 2434767459, 811589153,
       ]
     );
 
-    let grove_coords = find_grove_coords(&mixed_numbers);
+    let grove_coords = mixed.grove_coordinates(&GROVE_OFFSETS);
     assert_eq!(grove_coords, 1623178306);
   }
 
+  #[test]
+  fn test_decryption_key() {
+    let numbers = parse_input(&get_part_1_input());
+    assert_eq!(mix(&numbers, 10, 811589153).grove_coordinates(&GROVE_OFFSETS), 1623178306);
+  }
+
+  #[test]
+  #[should_panic(expected = "A decryption key of 0 can't be undone")]
+  fn test_unmix_zero_key() {
+    let numbers = parse_input(&get_part_1_input());
+    let mixed = mix(&numbers, 1, 0);
+    unmix(&mixed.values(), &mixed.original_positions(), 1, 0);
+  }
+
+  #[test]
+  fn test_unmix_round_trip() {
+    let numbers = parse_input(&get_part_1_input());
+    for rounds in 0..4 {
+      let mixed = mix(&numbers, rounds, 811589153);
+      let unmixed = unmix(&mixed.values(), &mixed.original_positions(), rounds, 811589153);
+      assert_eq!(unmixed, numbers);
+    }
+
+    // Undoing fewer rounds than were done gets the earlier mix back
+    let mixed = mix(&numbers, 3, 1);
+    let partly = unmix(&mixed.values(), &mixed.original_positions(), 2, 1);
+    let once = mix(&numbers, 1, 1).values();
+    let zero = partly.iter().position(|n| *n == 0).unwrap();
+    assert_eq!([&partly[zero..], &partly[..zero]].concat(), once);
+  }
+
+  #[test]
+  fn test_verify_mixed() {
+    let numbers = parse_input(&get_part_1_input());
+    assert_eq!(verify_mixed(&numbers, &[0, 3, -2, 1, 2, -3, 4], 1, 1), Ok(()));
+    assert_eq!(verify_mixed(&numbers, &[1, 2, -3, 4, 0, 3, -2], 1, 1), Ok(()));
+    assert_eq!(
+      verify_mixed(&numbers, &[0, 3, -2, 1, -3, 2, 4], 1, 1),
+      Err(MixMismatch::Number { position: 4, expected: 2, found: -3 })
+    );
+    assert_eq!(verify_mixed(&numbers, &[0, 3], 1, 1), Err(MixMismatch::Length { expected: 7, found: 2 }));
+    assert_eq!(
+      verify_mixed(&numbers, &[1, 2, -3, 4, 5, 3, -2], 1, 1).unwrap_err().to_string(),
+      "the mixed file doesn't contain a 0"
+    );
+  }
+
+  // Enough numbers for the list to be split into many blocks and rebuilt
+  // plenty of times, checked against a plain Vec doing the same moves
+  #[test]
+  fn test_matches_naive_mixing() {
+    let numbers: Vec<i64> = (0..500).map(|i: i64| (i * 7919 % 1009) - 504).chain([0]).collect();
+    let mut naive: Vec<usize> = (0..numbers.len()).collect();
+    for _ in 0..3 {
+      for index in 0..numbers.len() {
+        let position = naive.iter().position(|i| *i == index).unwrap();
+        naive.remove(position);
+        let target = (position as i64 + numbers[index]).rem_euclid(numbers.len() as i64 - 1) as usize;
+        naive.insert(target, index);
+      }
+    }
+    let naive: Vec<i64> = naive.iter().map(|i| numbers[*i]).collect();
+    let zero = naive.iter().position(|n| *n == 0).unwrap();
+    let expected: Vec<i64> = naive[zero..].iter().chain(naive[..zero].iter()).copied().collect();
+
+    assert_eq!(mix(&numbers, 3, 1).values(), expected);
+  }
+
   fn get_part_1_input() -> Vec<String> {
     vec![
       "1".to_string(),
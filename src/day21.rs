@@ -1,8 +1,7 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use lazy_static::lazy_static;
-use regex::Regex;
-
+use crate::day21_expr::{solve_all, Comparison, Expr, ExprError, Op, Rational};
 use crate::utils::read_chunks;
 
 pub fn part_one() -> i64 {
@@ -18,153 +17,255 @@ pub fn part_two() -> i64 {
 }
 
 fn solve_two(monkeys: &HashMap<String, String>) -> i64 {
-  let (root_monkey_left, _, root_monkey_right) = split_monkey_into_math_parts(monkeys.get("root").unwrap());
-  let orig_left_value = resolver(&monkeys, &root_monkey_left.to_string());
-  let orig_right_value = resolver(&monkeys, &root_monkey_right.to_string());
-
-  let mut monkeys_1 = monkeys.clone();
-  monkeys_1.insert("humn".to_string(), "1".to_string());
-  let new_left_value_1 = resolver(&monkeys_1, &root_monkey_left.to_string());
-
-  let monkeys_2 = monkeys.clone();
-  let new_left_value_2 = variable_resolver(&monkeys_2, &root_monkey_left.to_string());
-  let new_right_value_2 = variable_resolver(&monkeys_2, &root_monkey_right.to_string());
-
+  let values = humn_values(monkeys, Comparison::Equal).unwrap_or_else(|e| panic!("Unable to find humn: {}", e));
+  match values[..] {
+    [value] => value,
+    _ => panic!("humn could be any of {:?}", values),
+  }
+}
 
-  let (target_value, equation) = if new_left_value_1 == orig_left_value {
-    (orig_left_value, new_right_value_2)
-  } else {
-    (orig_right_value, new_left_value_2)
+// Every number humn could yell for root's comparison to hold
+pub fn humn_values(monkeys: &HashMap<String, String>, comparison: Comparison) -> Result<Vec<i64>, SolveError> {
+  let jobs = parse_jobs(monkeys)?;
+  let (left, right) = match jobs.get("root") {
+    Some(Job::Op(left, _, right)) => (*left, *right),
+    Some(Job::Number(_)) => return Err(SolveError::RootNotComparison),
+    None => return Err(GraphError::Undefined { monkey: "root".to_string(), needed_by: None }.into()),
   };
-
-  math_solver(target_value, equation)
+  let left = monkey_expr(&jobs, left, Some("humn"))?;
+  let right = monkey_expr(&jobs, right, Some("humn"))?;
+  Ok(solve_all(&left, &right, "humn", comparison)?)
 }
 
-fn math_solver(target_value: i64, equation: String) -> i64 {
-  // Need to reduce the expression some more
-  let mut chars = equation.chars();
-  chars.next();
-  chars.next_back();
-  let equation_stripped_one_parens = chars.as_str();
+fn solve_one(monkeys: &HashMap<String, String>) -> i64 {
+  let jobs = parse_jobs(monkeys).unwrap_or_else(|e| panic!("Invalid monkeys: {}", e));
+  match monkey_expr(&jobs, "root", None).unwrap_or_else(|e| panic!("Unable to work out root: {}", e)) {
+    Expr::Const(value) => value.to_integer().expect("root doesn't yell a whole number"),
+    _ => unreachable!("No variables so everything folds"),
+  }
+}
 
-  lazy_static! {
-        static ref RE_VAL_LEFT: Regex = Regex::new("^(\\d+) ([-+*/]) (\\(.*\\))$").unwrap();
-        static ref RE_VAL_RIGHT: Regex = Regex::new("^(\\(.*\\)) ([-+*/]) (\\d+)$").unwrap();
-    }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Job<'a> {
+  Number(i64),
+  Op(&'a str, Op, &'a str),
+}
 
-  if equation_stripped_one_parens.starts_with("(") {
-    let caps = RE_VAL_RIGHT.captures(equation_stripped_one_parens).unwrap();
-    let remaining_equation = caps[1].to_string();
-    let operator = caps[2].to_string();
-    let value = caps[3].to_string().parse::<i64>().unwrap();
-    match operator.as_str() {
-      "+" => math_solver(target_value - value, remaining_equation),
-      "-" => math_solver(target_value + value, remaining_equation),
-      "*" => math_solver(target_value / value, remaining_equation),
-      "/" => math_solver(target_value * value, remaining_equation),
-      _ => panic!("bad operator found")
+impl<'a> Job<'a> {
+  fn parse(job: &'a str) -> Option<Job<'a>> {
+    if let Ok(v) = job.parse() {
+      return Some(Job::Number(v));
     }
-  } else if equation_stripped_one_parens.ends_with(")") {
-    let caps = RE_VAL_LEFT.captures(equation_stripped_one_parens).unwrap();
-    let remaining_equation = caps[3].to_string();
-    let operator = caps[2].to_string();
-    let value = caps[1].to_string().parse::<i64>().unwrap();
-    match operator.as_str() {
-      // todo
-      "+" => math_solver(target_value - value, remaining_equation),
-      "-" => math_solver(-1 * (target_value - value), remaining_equation),
-      "*" => math_solver(target_value / value, remaining_equation),
-      "/" => math_solver( value / target_value, remaining_equation),
-      _ => panic!("bad operator found")
+    match job.split(' ').collect::<Vec<&str>>()[..] {
+      [m1, operator, m2] => Some(Job::Op(m1, Op::parse(operator)?, m2)),
+      _ => None,
     }
-  } else {
-    // We have the final step where humn is a value and some other final operation to take
-    let parts = equation_stripped_one_parens.split(" ").collect::<Vec<&str>>();
-    if parts[0] == "humn" {
-      match parts[1] {
-        "+" => target_value - parts[2].parse::<i64>().unwrap(),
-        "-" => target_value + parts[2].parse::<i64>().unwrap(),
-        "*" => target_value / parts[2].parse::<i64>().unwrap(),
-        "/" => target_value * parts[2].parse::<i64>().unwrap(),
-        _ => panic!("bad operator found")
-      }
-    } else {
-      match parts[1] {
-        // todo
-        "+" => target_value - parts[0].parse::<i64>().unwrap(),
-        "-" => -1 * (target_value - parts[0].parse::<i64>().unwrap()),
-        "*" => target_value / parts[0].parse::<i64>().unwrap(),
-        "/" =>  parts[0].parse::<i64>().unwrap() / target_value,
-        _ => panic!("bad operator found")
-      }
+  }
+
+  fn dependencies(&self) -> Vec<&'a str> {
+    match self {
+      Job::Number(_) => vec![],
+      Job::Op(m1, _, m2) => vec![m1, m2],
     }
   }
 }
 
-fn solve_one(monkeys: &HashMap<String, String>) -> i64 {
-  resolver(monkeys, &"root".to_string())
+// Problems with how the monkeys depend on each other. A cycle lists the
+// monkeys in it, starting and ending with the same one
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphError {
+  InvalidJob { monkey: String, job: String },
+  Undefined { monkey: String, needed_by: Option<String> },
+  Cycle(Vec<String>),
 }
 
+impl fmt::Display for GraphError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GraphError::InvalidJob { monkey, job } => write!(f, "{} has a job that isn't a number or an operation: '{}'", monkey, job),
+      GraphError::Undefined { monkey, needed_by: Some(needed_by) } => write!(f, "{} needs {} but there's no such monkey", needed_by, monkey),
+      GraphError::Undefined { monkey, needed_by: None } => write!(f, "there's no monkey called {}", monkey),
+      GraphError::Cycle(monkeys) => write!(f, "monkeys wait on each other in a cycle: {}", monkeys.join(" -> ")),
+    }
+  }
+}
 
-fn resolver(monkeys: &HashMap<String, String>, target_monkey_name: &String) -> i64 {
-  let monkey_job = monkeys.get(target_monkey_name).unwrap();
-  match monkey_job.parse::<i64>() {
-    Ok(v) => { return v; }
-    Err(_) => {
-      let (m1, operator, m2) = split_monkey_into_math_parts(monkey_job);
-      let m1 = resolver(monkeys, &m1.to_string());
-      let m2 = resolver(monkeys, &m2.to_string());
+// Everything that can stop us finding what humn should yell
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveError {
+  Graph(GraphError),
+  Expr(ExprError),
+  RootNotComparison,
+}
 
-      match operator {
-        "+" => { m1 + m2 }
-        "-" => { m1 - m2 }
-        "*" => { m1 * m2 }
-        "/" => { m1 / m2 }
-        _ => { panic!("uh oh, bad operator") }
-      }
+impl fmt::Display for SolveError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SolveError::Graph(e) => write!(f, "{}", e),
+      SolveError::Expr(e) => write!(f, "{}", e),
+      SolveError::RootNotComparison => write!(f, "root has to compare two monkeys"),
     }
   }
 }
 
-fn variable_resolver(monkeys: &HashMap<String, String>, target_monkey_name: &String) -> String {
-  let monkey_job = monkeys.get(target_monkey_name).unwrap();
-  if target_monkey_name == "humn" {
-    return "humn".to_string();
+impl From<GraphError> for SolveError {
+  fn from(e: GraphError) -> SolveError {
+    SolveError::Graph(e)
   }
+}
 
-  match monkey_job.parse::<i64>() {
-    Ok(v) => { return v.to_string(); }
-    Err(_) => {
-      let (m1, operator, m2) = split_monkey_into_math_parts(monkey_job);
-      let m1 = variable_resolver(monkeys, &m1.to_string());
-      let m2 = variable_resolver(monkeys, &m2.to_string());
+impl From<ExprError> for SolveError {
+  fn from(e: ExprError) -> SolveError {
+    SolveError::Expr(e)
+  }
+}
 
-      if m1.contains("humn") || m2.contains("humn") {
-        return format!("({} {} {})", m1, operator, m2);
-      }
+pub fn parse_jobs(monkeys: &HashMap<String, String>) -> Result<HashMap<&str, Job<'_>>, GraphError> {
+  monkeys
+    .iter()
+    .map(|(monkey, job)| match Job::parse(job) {
+      Some(parsed) => Ok((monkey.as_str(), parsed)),
+      None => Err(GraphError::InvalidJob { monkey: monkey.clone(), job: job.clone() }),
+    })
+    .collect()
+}
+
+// Every monkey `root` depends on, including itself, each one after all the
+// monkeys it needs. Walks the graph with an explicit stack so a long chain of
+// monkeys can't overflow anything
+pub fn dependency_order<'a>(jobs: &HashMap<&'a str, Job<'a>>, root: &'a str) -> Result<Vec<&'a str>, GraphError> {
+  if !jobs.contains_key(root) {
+    return Err(GraphError::Undefined { monkey: root.to_string(), needed_by: None });
+  }
 
-      let result: i64 = match operator {
-        "+" => { m1.parse::<i64>().unwrap() + m2.parse::<i64>().unwrap() }
-        "-" => { m1.parse::<i64>().unwrap() - m2.parse::<i64>().unwrap() }
-        "*" => { m1.parse::<i64>().unwrap() * m2.parse::<i64>().unwrap() }
-        "/" => { m1.parse::<i64>().unwrap() / m2.parse::<i64>().unwrap() }
-        _ => { panic!("uh oh, bad operator") }
-      };
+  let mut order = vec![];
+  // false while a monkey is on the stack, true once it's in `order`
+  let mut finished: HashMap<&str, bool> = HashMap::from([(root, false)]);
+  let mut stack: Vec<(&str, usize)> = vec![(root, 0)];
+
+  while let Some(&(monkey, next)) = stack.last() {
+    let dependencies = jobs[monkey].dependencies();
+    if next == dependencies.len() {
+      finished.insert(monkey, true);
+      order.push(monkey);
+      stack.pop();
+      continue;
+    }
 
-      return format!("{}", result);
+    stack.last_mut().unwrap().1 += 1;
+    let dependency = dependencies[next];
+    match finished.get(dependency) {
+      Some(true) => {}
+      Some(false) => {
+        let start = stack.iter().position(|(m, _)| *m == dependency).unwrap();
+        let mut cycle: Vec<String> = stack[start..].iter().map(|(m, _)| m.to_string()).collect();
+        cycle.push(dependency.to_string());
+        return Err(GraphError::Cycle(cycle));
+      }
+      None if !jobs.contains_key(dependency) => {
+        return Err(GraphError::Undefined { monkey: dependency.to_string(), needed_by: Some(monkey.to_string()) });
+      }
+      None => {
+        finished.insert(dependency, false);
+        stack.push((dependency, 0));
+      }
     }
   }
+
+  Ok(order)
 }
 
+// The expression a monkey yells, with `unknown` left as a variable rather than
+// the number it was given. Everything that doesn't depend on it gets folded
+fn monkey_expr(jobs: &HashMap<&str, Job>, name: &str, unknown: Option<&str>) -> Result<Expr, SolveError> {
+  let order = dependency_order(jobs, name)?;
+  Ok(build_expr(jobs, &order, unknown)?)
+}
 
-fn split_monkey_into_math_parts(monkey_job: &String) -> (&str, &str, &str) {
-  let parts = monkey_job.split(" ").collect::<Vec<&str>>();
+// Works through the monkeys in dependency order so each one's expression is
+// built exactly once from the ones it needs, returning the last monkey's. A
+// monkey's expression is moved into whoever needs it last rather than copied,
+// so chains don't get copied over and over
+fn build_expr(jobs: &HashMap<&str, Job>, order: &[&str], unknown: Option<&str>) -> Result<Expr, ExprError> {
+  let mut uses: HashMap<&str, usize> = HashMap::new();
+  for monkey in order {
+    for dependency in jobs[monkey].dependencies() {
+      *uses.entry(dependency).or_insert(0) += 1;
+    }
+  }
 
-  (parts.get(0).unwrap(), parts.get(1).unwrap(), parts.get(2).unwrap())
+  let mut exprs: HashMap<&str, Expr> = HashMap::new();
+  let mut take = |exprs: &mut HashMap<&str, Expr>, monkey: &str| {
+    let left = uses.get_mut(monkey).unwrap();
+    *left -= 1;
+    if *left == 0 { exprs.remove(monkey).unwrap() } else { exprs[monkey].clone() }
+  };
+  for monkey in order {
+    let expr = match jobs[monkey] {
+      _ if unknown == Some(*monkey) => Expr::Var(monkey.to_string()),
+      Job::Number(v) => Expr::Const(Rational::integer(v)),
+      Job::Op(m1, op, m2) => {
+        let left = take(&mut exprs, m1);
+        Expr::bin(left, op, take(&mut exprs, m2))?
+      }
+    };
+    exprs.insert(monkey, expr);
+  }
+  Ok(exprs.remove(order.last().unwrap()).unwrap())
 }
 
+// The monkeys going from `from` down to `to` through the things they depend on
+fn dependency_path<'a>(jobs: &HashMap<&'a str, Job<'a>>, from: &'a str, to: &str) -> Option<Vec<&'a str>> {
+  let mut came_from: HashMap<&str, &str> = HashMap::new();
+  let mut stack = vec![from];
+  while let Some(monkey) = stack.pop() {
+    if monkey == to {
+      let mut path = vec![monkey];
+      while let Some(previous) = came_from.get(path.last().unwrap()) {
+        path.push(previous);
+      }
+      path.reverse();
+      return Some(path);
+    }
+    for dependency in jobs.get(monkey).map_or(vec![], |j| j.dependencies()) {
+      if dependency != from && !came_from.contains_key(dependency) {
+        came_from.insert(dependency, monkey);
+        stack.push(dependency);
+      }
+    }
+  }
+  None
+}
+
+// Graphviz DOT of who waits on whom, with the chain from `from` to `to`
+// drawn in red
+pub fn to_dot(jobs: &HashMap<&str, Job>, from: &str, to: &str) -> String {
+  let path = dependency_path(jobs, from, to).unwrap_or_default();
+  let on_path = |m: &str| path.contains(&m);
+  let mut monkeys: Vec<&&str> = jobs.keys().collect();
+  monkeys.sort();
+
+  let mut dot = String::from("digraph monkeys {\n");
+  for monkey in &monkeys {
+    let label = match jobs[**monkey] {
+      Job::Number(v) => format!("{}: {}", monkey, v),
+      Job::Op(_, op, _) => format!("{}: {}", monkey, op.symbol()),
+    };
+    let style = if on_path(monkey) { ", color=red, fontcolor=red" } else { "" };
+    dot.push_str(&format!("  \"{}\" [label=\"{}\"{}];\n", monkey, label, style));
+  }
+  for monkey in &monkeys {
+    for dependency in jobs[**monkey].dependencies() {
+      let on_path = path.windows(2).any(|w| w[0] == **monkey && w[1] == dependency);
+      let style = if on_path { " [color=red, penwidth=2]" } else { "" };
+      dot.push_str(&format!("  \"{}\" -> \"{}\"{};\n", monkey, dependency, style));
+    }
+  }
+  dot.push_str("}\n");
+  dot
+}
 
-fn parse_input(lines: &Vec<String>) -> HashMap<String, String> {
+pub fn parse_input(lines: &[String]) -> HashMap<String, String> {
   lines
     .iter()
     .map(|s| s.split(":"))
@@ -179,7 +280,14 @@ fn parse_input(lines: &Vec<String>) -> HashMap<String, String> {
 
 #[cfg(test)]
 mod tests {
-  use crate::day21::{math_solver, parse_input, solve_one, solve_two};
+  use std::collections::HashMap;
+
+  use crate::day21::{dependency_order, humn_values, parse_input, parse_jobs, solve_one, solve_two, to_dot, GraphError, SolveError};
+  use crate::day21_expr::{Comparison, ExprError};
+
+  fn monkeys(lines: &[&str]) -> HashMap<String, String> {
+    parse_input(&lines.iter().map(|l| l.to_string()).collect::<Vec<String>>())
+  }
 
   #[test]
   fn test_part_1() {
@@ -198,35 +306,108 @@ mod tests {
   }
 
   #[test]
-  fn test_solve_expr_with_humn_sec_1_subtract() {
-    let target_value = 1;
-    let equation = "(5 + (2 - humn))".to_string();
-    let res = math_solver(target_value, equation);
-    assert_eq!(res, 6);
+  fn test_humn_on_right_of_root() {
+    let mut input = get_part_1_input();
+    input[0] = "root: sjmn + pppw".to_string();
+    let monkeys = parse_input(&input);
+    assert_eq!(solve_two(&monkeys), 301);
+  }
+
+  #[test]
+  fn test_humn_used_twice() {
+    // (humn - 3) * 2 = humn + 4
+    let monkeys = monkeys(&[
+      "root: aaaa + bbbb", "aaaa: cccc * two", "cccc: humn - tree", "bbbb: humn + four",
+      "humn: 1", "two: 2", "tree: 3", "four: 4",
+    ]);
+    assert_eq!(humn_values(&monkeys, Comparison::Equal), Ok(vec![10]));
+    assert_eq!(humn_values(&monkeys, Comparison::DifferenceZero), Ok(vec![10]));
+  }
+
+  #[test]
+  fn test_dependency_order() {
+    let monkeys = parse_input(&get_part_1_input());
+    let jobs = parse_jobs(&monkeys).unwrap();
+    let order = dependency_order(&jobs, "root").unwrap();
+    assert_eq!(order.len(), 15);
+    assert_eq!(order.last(), Some(&"root"));
+    let position = |m: &str| order.iter().position(|o| *o == m).unwrap();
+    assert!(position("humn") < position("ptdq"));
+    assert!(position("ptdq") < position("lgvd"));
+
+    assert_eq!(dependency_order(&jobs, "ptdq").unwrap().len(), 3);
+  }
+
+  #[test]
+  fn test_graph_errors() {
+    let cyclic = monkeys(&["root: aaaa + bbbb", "aaaa: 4", "bbbb: cccc * aaaa", "cccc: bbbb - aaaa"]);
+    let jobs = parse_jobs(&cyclic).unwrap();
+    let error = dependency_order(&jobs, "root").unwrap_err();
+    assert_eq!(error, GraphError::Cycle(vec!["bbbb".to_string(), "cccc".to_string(), "bbbb".to_string()]));
+    assert_eq!(error.to_string(), "monkeys wait on each other in a cycle: bbbb -> cccc -> bbbb");
+
+    let missing = monkeys(&["root: aaaa + bbbb", "aaaa: 4"]);
+    let jobs = parse_jobs(&missing).unwrap();
+    assert_eq!(dependency_order(&jobs, "root").unwrap_err().to_string(), "root needs bbbb but there's no such monkey");
+    assert_eq!(dependency_order(&jobs, "zzzz").unwrap_err().to_string(), "there's no monkey called zzzz");
+
+    let invalid = monkeys(&["root: aaaa % bbbb"]);
+    assert_eq!(
+      parse_jobs(&invalid).unwrap_err(),
+      GraphError::InvalidJob { monkey: "root".to_string(), job: "aaaa % bbbb".to_string() }
+    );
+  }
+
+  #[test]
+  fn test_humn_values_errors() {
+    let cyclic = monkeys(&["root: humn + bbbb", "humn: 4", "bbbb: cccc * humn", "cccc: bbbb - humn"]);
+    let error = humn_values(&cyclic, Comparison::Equal).unwrap_err();
+    assert_eq!(error, SolveError::Graph(GraphError::Cycle(vec!["bbbb".to_string(), "cccc".to_string(), "bbbb".to_string()])));
+
+    let missing = monkeys(&["root: humn + bbbb", "humn: 4"]);
+    assert_eq!(humn_values(&missing, Comparison::Equal).unwrap_err().to_string(), "there's no monkey called bbbb");
+    let no_root = monkeys(&["humn: 4"]);
+    assert_eq!(humn_values(&no_root, Comparison::Equal).unwrap_err().to_string(), "there's no monkey called root");
+    let number_root = monkeys(&["root: 5", "humn: 4"]);
+    assert_eq!(humn_values(&number_root, Comparison::Equal), Err(SolveError::RootNotComparison));
+
+    let divide_by_zero = monkeys(&["root: humn + bbbb", "humn: 4", "bbbb: cccc / dddd", "cccc: 3", "dddd: 0"]);
+    assert_eq!(humn_values(&divide_by_zero, Comparison::Equal), Err(SolveError::Expr(ExprError::DivisionByZero)));
   }
 
   #[test]
-  fn test_solve_expr_with_humn_sec_2_subtract() {
-    let target_value = 1;
-    let equation = "(5 - (2 - humn))".to_string();
-    let res = math_solver(target_value, equation);
-    assert_eq!(res, -2);
+  fn test_long_chain() {
+    let mut lines: Vec<String> = (0..100_000).map(|i| format!("m{}: m{} + one", i, i + 1)).collect();
+    lines.push("m100000: 5".to_string());
+    lines.push("one: 1".to_string());
+    lines.push("root: m0 + one".to_string());
+    assert_eq!(solve_one(&parse_input(&lines)), 100_006);
   }
 
   #[test]
-  fn test_solve_expr_with_humn_sec_1_divide() {
-    let target_value = 1;
-    let equation = "(5 + (8 / humn))".to_string();
-    let res = math_solver(target_value, equation);
-    assert_eq!(res, -2);
+  fn test_long_chain_through_humn() {
+    // m0 = m1 + one, ..., m19999 = humn + one, so m0 = humn + 20000
+    let mut lines: Vec<String> = (0..19_999).map(|i| format!("m{}: m{} + one", i, i + 1)).collect();
+    lines.push("m19999: humn + one".to_string());
+    lines.push("humn: 0".to_string());
+    lines.push("one: 1".to_string());
+    lines.push("goal: 20500".to_string());
+    lines.push("root: m0 + goal".to_string());
+    assert_eq!(humn_values(&parse_input(&lines), Comparison::Equal), Ok(vec![500]));
+    assert_eq!(humn_values(&parse_input(&lines), Comparison::DifferenceZero), Ok(vec![500]));
   }
 
   #[test]
-  fn test_solve_expr_with_humn_sec_2_divide() {
-    let target_value = 1;
-    let equation = "(4 / (8 / humn))".to_string();
-    let res = math_solver(target_value, equation);
-    assert_eq!(res, 2);
+  fn test_dot_export() {
+    let monkeys = parse_input(&get_part_1_input());
+    let jobs = parse_jobs(&monkeys).unwrap();
+    let dot = to_dot(&jobs, "root", "humn");
+    assert!(dot.starts_with("digraph monkeys {"));
+    assert!(dot.contains("\"root\" -> \"pppw\" [color=red, penwidth=2];"));
+    assert!(dot.contains("\"root\" -> \"sjmn\";"));
+    assert!(dot.contains("\"humn\" [label=\"humn: 5\", color=red, fontcolor=red];"));
+    assert!(dot.contains("\"lgvd\" [label=\"lgvd: *\", color=red, fontcolor=red];"));
+    assert_eq!(dot.matches("color=red, penwidth=2").count(), 5);
   }
 
   fn get_part_1_input() -> Vec<String> {
@@ -0,0 +1,646 @@
+use std::fmt;
+
+// An exact fraction, always kept in lowest terms with a positive denominator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rational {
+  num: i128,
+  den: i128,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+  if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+impl Rational {
+  pub fn new(num: i128, den: i128) -> Rational {
+    assert_ne!(den, 0, "Rational with a zero denominator");
+    let divisor = gcd(num, den) * den.signum();
+    Rational { num: num / divisor, den: den / divisor }
+  }
+
+  pub fn integer(value: i64) -> Rational {
+    Rational { num: value as i128, den: 1 }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.num == 0
+  }
+
+  pub fn to_integer(self) -> Option<i64> {
+    match self.den {
+      1 => i64::try_from(self.num).ok(),
+      _ => None,
+    }
+  }
+
+  fn apply(self, op: Op, other: Rational) -> Result<Rational, ExprError> {
+    let mul = |a: i128, b: i128| a.checked_mul(b).ok_or(ExprError::Overflow);
+    Ok(match op {
+      Op::Add | Op::Sub => {
+        let right = mul(other.num, self.den)?;
+        let right = if op == Op::Sub { right.checked_neg().ok_or(ExprError::Overflow)? } else { right };
+        let num = mul(self.num, other.den)?.checked_add(right).ok_or(ExprError::Overflow)?;
+        Rational::new(num, mul(self.den, other.den)?)
+      }
+      Op::Mul => Rational::new(mul(self.num, other.num)?, mul(self.den, other.den)?),
+      Op::Div if other.is_zero() => return Err(ExprError::DivisionByZero),
+      Op::Div => Rational::new(mul(self.num, other.den)?, mul(self.den, other.num)?),
+    })
+  }
+}
+
+impl fmt::Display for Rational {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.den {
+      1 => write!(f, "{}", self.num),
+      _ => write!(f, "{}/{}", self.num, self.den),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+  Add,
+  Sub,
+  Mul,
+  Div,
+}
+
+impl Op {
+  pub fn parse(text: &str) -> Option<Op> {
+    match text {
+      "+" => Some(Op::Add),
+      "-" => Some(Op::Sub),
+      "*" => Some(Op::Mul),
+      "/" => Some(Op::Div),
+      _ => None,
+    }
+  }
+
+  pub fn symbol(&self) -> &'static str {
+    match self {
+      Op::Add => "+",
+      Op::Sub => "-",
+      Op::Mul => "*",
+      Op::Div => "/",
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+  Const(Rational),
+  Var(String),
+  BinOp(Box<Expr>, Op, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprError {
+  DivisionByZero,
+  VariableMissing(String),
+  VariableRepeated(String, usize),
+  NotIntegral(Rational),
+  Overflow,
+  NoSolution,
+  AnyValue,
+  DegreeTooHigh(usize),
+}
+
+impl fmt::Display for ExprError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ExprError::DivisionByZero => write!(f, "division by zero"),
+      ExprError::VariableMissing(name) => write!(f, "{} doesn't appear in the equation", name),
+      ExprError::VariableRepeated(name, count) => write!(f, "{} appears {} times in the equation", name, count),
+      ExprError::NotIntegral(value) => write!(f, "the solution {} isn't a whole number", value),
+      ExprError::Overflow => write!(f, "the numbers got too big"),
+      ExprError::NoSolution => write!(f, "no whole number makes the two sides equal"),
+      ExprError::AnyValue => write!(f, "the two sides are equal whatever the value"),
+      ExprError::DegreeTooHigh(degree) => write!(f, "can't solve an equation of degree {}", degree),
+    }
+  }
+}
+
+impl Expr {
+  // Joins two expressions, folding them straight into a constant when neither
+  // side has a variable in it
+  pub fn bin(left: Expr, op: Op, right: Expr) -> Result<Expr, ExprError> {
+    match (&left, &right) {
+      (Expr::Const(l), Expr::Const(r)) => Ok(Expr::Const(l.apply(op, *r)?)),
+      _ => Ok(Expr::BinOp(Box::new(left), op, Box::new(right))),
+    }
+  }
+
+  // Works out a value for every node from the bottom up, using a stack rather
+  // than recursion so that long chains of monkeys don't run out of stack
+  fn fold<T>(
+    &self,
+    leaf: impl Fn(&Expr) -> Result<T, ExprError>,
+    combine: impl Fn(T, Op, T) -> Result<T, ExprError>,
+  ) -> Result<T, ExprError> {
+    let mut to_visit = vec![(self, false)];
+    let mut values: Vec<T> = vec![];
+    while let Some((expr, children_done)) = to_visit.pop() {
+      match expr {
+        Expr::BinOp(_, op, _) if children_done => {
+          let r = values.pop().unwrap();
+          let l = values.pop().unwrap();
+          values.push(combine(l, *op, r)?);
+        }
+        Expr::BinOp(left, _, right) => {
+          to_visit.push((expr, true));
+          to_visit.push((right, false));
+          to_visit.push((left, false));
+        }
+        _ => values.push(leaf(expr)?),
+      }
+    }
+    Ok(values.pop().unwrap())
+  }
+
+  pub fn count_var(&self, name: &str) -> usize {
+    self
+      .fold(
+        |leaf| Ok(matches!(leaf, Expr::Var(v) if v == name) as usize),
+        |l, _, r| Ok(l + r),
+      )
+      .unwrap()
+  }
+
+  // The exact value with `var` set to `value`
+  pub fn evaluate(&self, var: &str, value: Rational) -> Result<Rational, ExprError> {
+    self.fold(
+      |leaf| match leaf {
+        Expr::Const(c) => Ok(*c),
+        Expr::Var(v) if v == var => Ok(value),
+        Expr::Var(v) => Err(ExprError::VariableMissing(v.clone())),
+        Expr::BinOp(..) => unreachable!("Operations aren't leaves"),
+      },
+      |l, op, r| l.apply(op, r),
+    )
+  }
+
+  // The expression as one polynomial over another. Nothing is cancelled, so
+  // the denominator is zero wherever any division in the tree would be
+  fn to_fraction(&self, var: &str) -> Result<(Polynomial, Polynomial), ExprError> {
+    self.fold(
+      |leaf| match leaf {
+        Expr::Const(c) => Ok((Polynomial(vec![*c]), Polynomial::one())),
+        Expr::Var(v) if v == var => Ok((Polynomial(vec![Rational::integer(0), Rational::integer(1)]), Polynomial::one())),
+        Expr::Var(v) => Err(ExprError::VariableMissing(v.clone())),
+        Expr::BinOp(..) => unreachable!("Operations aren't leaves"),
+      },
+      |(a, b), op, (c, d)| {
+        Ok(match op {
+          Op::Add => (a.mul(&d)?.add(&c.mul(&b)?, Op::Add)?, b.mul(&d)?),
+          Op::Sub => (a.mul(&d)?.add(&c.mul(&b)?, Op::Sub)?, b.mul(&d)?),
+          Op::Mul => (a.mul(&c)?, b.mul(&d)?),
+          Op::Div if c.is_zero() => return Err(ExprError::DivisionByZero),
+          Op::Div => (a.mul(&d)?, b.mul(&c)?),
+        })
+      },
+    )
+  }
+}
+
+// Copied from the bottom up for the same reason as `fold`
+impl Clone for Expr {
+  fn clone(&self) -> Expr {
+    self
+      .fold(
+        |leaf| Ok(match leaf {
+          Expr::Const(c) => Expr::Const(*c),
+          Expr::Var(v) => Expr::Var(v.clone()),
+          Expr::BinOp(..) => unreachable!("Operations aren't leaves"),
+        }),
+        |l, op, r| Ok(Expr::BinOp(Box::new(l), op, Box::new(r))),
+      )
+      .unwrap()
+  }
+}
+
+// Dropping a deep tree would otherwise recurse once per level too, so the
+// children are unhooked onto a stack first
+impl Drop for Expr {
+  fn drop(&mut self) {
+    let mut stack = vec![];
+    let unhook = |expr: &mut Expr, stack: &mut Vec<Expr>| {
+      if let Expr::BinOp(left, _, right) = expr {
+        stack.push(std::mem::replace(left.as_mut(), Expr::Const(Rational::integer(0))));
+        stack.push(std::mem::replace(right.as_mut(), Expr::Const(Rational::integer(0))));
+      }
+    };
+    unhook(self, &mut stack);
+    while let Some(mut expr) = stack.pop() {
+      unhook(&mut expr, &mut stack);
+    }
+  }
+}
+
+// Coefficients from the constant term up, with no trailing zeros
+#[derive(Clone, Debug, PartialEq)]
+struct Polynomial(Vec<Rational>);
+
+impl Polynomial {
+  fn one() -> Polynomial {
+    Polynomial(vec![Rational::integer(1)])
+  }
+
+  fn trimmed(mut coefficients: Vec<Rational>) -> Polynomial {
+    while coefficients.last().is_some_and(|c| c.is_zero()) {
+      coefficients.pop();
+    }
+    Polynomial(coefficients)
+  }
+
+  fn is_zero(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  fn degree(&self) -> usize {
+    self.0.len().saturating_sub(1)
+  }
+
+  // Adds or subtracts, depending on `op`
+  fn add(&self, other: &Polynomial, op: Op) -> Result<Polynomial, ExprError> {
+    let zero = Rational::integer(0);
+    let coefficients = (0..self.0.len().max(other.0.len()))
+      .map(|i| self.0.get(i).unwrap_or(&zero).apply(op, *other.0.get(i).unwrap_or(&zero)))
+      .collect::<Result<Vec<Rational>, ExprError>>()?;
+    Ok(Polynomial::trimmed(coefficients))
+  }
+
+  fn mul(&self, other: &Polynomial) -> Result<Polynomial, ExprError> {
+    if self.is_zero() || other.is_zero() {
+      return Ok(Polynomial(vec![]));
+    }
+    let mut coefficients = vec![Rational::integer(0); self.0.len() + other.0.len() - 1];
+    for (i, a) in self.0.iter().enumerate() {
+      for (j, b) in other.0.iter().enumerate() {
+        coefficients[i + j] = coefficients[i + j].apply(Op::Add, a.apply(Op::Mul, *b)?)?;
+      }
+    }
+    Ok(Polynomial::trimmed(coefficients))
+  }
+
+  // The same roots but with whole number coefficients, by multiplying through
+  // by every denominator
+  fn to_integers(&self) -> Result<Vec<i128>, ExprError> {
+    let scale = self.0.iter().try_fold(1i128, |lcm, c| {
+      (lcm / gcd(lcm, c.den)).checked_mul(c.den).ok_or(ExprError::Overflow)
+    })?;
+    self.0.iter().map(|c| c.num.checked_mul(scale / c.den).ok_or(ExprError::Overflow)).collect()
+  }
+
+  // Every whole number root. Once the zero roots are divided out, linear and
+  // quadratic equations are solved exactly. Anything higher has its real roots
+  // found numerically inside the Cauchy bound, and the whole numbers next to
+  // each are checked exactly
+  fn integer_roots(&self) -> Result<Vec<i64>, ExprError> {
+    let coefficients = self.to_integers()?;
+    let zeros = coefficients.iter().take_while(|c| **c == 0).count();
+    let reduced = &coefficients[zeros..];
+    let mut roots = if zeros > 0 { vec![0] } else { vec![] };
+
+    let evaluates_to_zero = |x: i128| {
+      reduced
+        .iter()
+        .rev()
+        .try_fold(0i128, |total, c| total.checked_mul(x)?.checked_add(*c))
+        .is_some_and(|v| v == 0)
+    };
+    let candidates = match reduced.len() - 1 {
+      0 => vec![],
+      1 => vec![exact_quotient(-reduced[0], reduced[1])].into_iter().flatten().collect(),
+      2 => {
+        let (a, b, c) = (reduced[2], reduced[1], reduced[0]);
+        let discriminant = b.checked_mul(b)
+          .zip(a.checked_mul(c).and_then(|ac| ac.checked_mul(4)))
+          .and_then(|(b2, ac4)| b2.checked_sub(ac4))
+          .ok_or(ExprError::Overflow)?;
+        if discriminant < 0 {
+          vec![]
+        } else {
+          let root = discriminant.unsigned_abs().isqrt() as i128;
+          if root * root != discriminant {
+            vec![]
+          } else {
+            [-b + root, -b - root].iter().filter_map(|n| exact_quotient(*n, 2 * a)).collect()
+          }
+        }
+      }
+      _ => {
+        let floats: Vec<f64> = reduced.iter().map(|c| *c as f64).collect();
+        let leading = floats.last().unwrap().abs();
+        let bound = 1.0 + floats.iter().rev().skip(1).map(|c| c.abs() / leading).fold(0.0, f64::max);
+        real_roots(&floats, bound)
+          .iter()
+          .flat_map(|x| {
+            let x = x.round() as i128;
+            [x - 1, x, x + 1]
+          })
+          .collect()
+      }
+    };
+
+    for x in candidates {
+      if !roots.contains(&(x as i64)) && evaluates_to_zero(x) {
+        roots.push(x as i64);
+      }
+    }
+    roots.sort();
+    Ok(roots)
+  }
+}
+
+fn exact_quotient(num: i128, den: i128) -> Option<i128> {
+  if num % den == 0 { Some(num / den) } else { None }
+}
+
+fn evaluate_float(coefficients: &[f64], x: f64) -> f64 {
+  coefficients.iter().rev().fold(0.0, |total, c| total * x + c)
+}
+
+// Roughly where a polynomial crosses or touches zero within -bound..bound. It
+// only changes direction at the roots of its derivative, so between those it
+// crosses zero at most once and can be bisected. The turning points are kept
+// too, to catch roots it only touches
+fn real_roots(coefficients: &[f64], bound: f64) -> Vec<f64> {
+  if coefficients.len() < 2 {
+    return vec![];
+  }
+  if coefficients.len() == 2 {
+    return vec![-coefficients[0] / coefficients[1]];
+  }
+  let derivative: Vec<f64> = coefficients.iter().enumerate().skip(1).map(|(i, c)| c * i as f64).collect();
+  let mut points = vec![-bound];
+  let mut turning: Vec<f64> = real_roots(&derivative, bound).into_iter().filter(|x| x.abs() < bound).collect();
+  turning.sort_by(|a, b| a.partial_cmp(b).unwrap());
+  points.extend(turning.iter());
+  points.push(bound);
+
+  let mut roots = turning;
+  for pair in points.windows(2) {
+    let (mut low, mut high) = (pair[0], pair[1]);
+    let low_sign = evaluate_float(coefficients, low).signum();
+    if low_sign == evaluate_float(coefficients, high).signum() {
+      continue;
+    }
+    for _ in 0..200 {
+      let middle = (low + high) / 2.0;
+      if evaluate_float(coefficients, middle).signum() == low_sign {
+        low = middle;
+      } else {
+        high = middle;
+      }
+    }
+    roots.push(low);
+  }
+  roots
+}
+
+// How `root` compares its two monkeys. Both find the same numbers: `Equal`
+// solves left = right, `DifferenceZero` solves the single expression
+// left - right = 0
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+  Equal,
+  DifferenceZero,
+}
+
+impl Comparison {
+  pub fn parse(text: &str) -> Option<Comparison> {
+    match text {
+      "equal" => Some(Comparison::Equal),
+      "difference" => Some(Comparison::DifferenceZero),
+      _ => None,
+    }
+  }
+}
+
+pub const MAX_DEGREE: usize = 4;
+
+// Every whole number `var` could be for the comparison to hold. When the
+// variable only appears once it's isolated directly, otherwise both sides are
+// reduced to a single polynomial equation. Linear ones are solved exactly, and
+// whole number roots are found for anything up to `MAX_DEGREE`
+pub fn solve_all(left: &Expr, right: &Expr, var: &str, comparison: Comparison) -> Result<Vec<i64>, ExprError> {
+  let (left, right) = match comparison {
+    Comparison::Equal => (left.clone(), right.clone()),
+    Comparison::DifferenceZero => (Expr::bin(left.clone(), Op::Sub, right.clone())?, Expr::Const(Rational::integer(0))),
+  };
+  if left.count_var(var) + right.count_var(var) == 1 {
+    return solve_for(&left, &right, var).map(|x| vec![x]);
+  }
+
+  // a/b = c/d exactly when ad - cb = 0, as long as neither b or d are zero
+  let (a, b) = left.to_fraction(var)?;
+  let (c, d) = right.to_fraction(var)?;
+  let equation = a.mul(&d)?.add(&c.mul(&b)?, Op::Sub)?;
+
+  let candidates = match equation.degree() {
+    _ if equation.is_zero() => return Err(ExprError::AnyValue),
+    0 => vec![],
+    1 => {
+      let root = equation.0[0].apply(Op::Div, equation.0[1])?.apply(Op::Mul, Rational::integer(-1))?;
+      match root.to_integer() {
+        Some(root) => vec![root],
+        None => return Err(ExprError::NotIntegral(root)),
+      }
+    }
+    degree if degree > MAX_DEGREE => return Err(ExprError::DegreeTooHigh(degree)),
+    _ => equation.integer_roots()?,
+  };
+
+  // Throws away anything that only worked by multiplying out a zero division
+  let roots: Vec<i64> = candidates
+    .into_iter()
+    .filter(|x| {
+      let x = Rational::integer(*x);
+      matches!((left.evaluate(var, x), right.evaluate(var, x)), (Ok(l), Ok(r)) if l == r)
+    })
+    .collect();
+  if roots.is_empty() {
+    return Err(ExprError::NoSolution);
+  }
+  Ok(roots)
+}
+
+// Finds the whole number `var` has to be for `left` and `right` to be equal.
+// The variable has to appear exactly once, and everything else has to fold
+// down to a constant, so it can be isolated by undoing one operation at a time
+pub fn solve_for(left: &Expr, right: &Expr, var: &str) -> Result<i64, ExprError> {
+  let (mut expr, mut target) = match (left, right) {
+    (_, Expr::Const(value)) => (left, *value),
+    (Expr::Const(value), _) => (right, *value),
+    _ => {
+      let count = left.count_var(var) + right.count_var(var);
+      return Err(if count == 0 { ExprError::VariableMissing(var.to_string()) } else { ExprError::VariableRepeated(var.to_string(), count) });
+    }
+  };
+  match expr.count_var(var) {
+    1 => {}
+    0 => return Err(ExprError::VariableMissing(var.to_string())),
+    count => return Err(ExprError::VariableRepeated(var.to_string(), count)),
+  }
+
+  loop {
+    match expr {
+      Expr::Var(_) => break,
+      // Only one side can have the variable, so the other has been folded
+      Expr::BinOp(l, op, r) => match (l.as_ref(), r.as_ref()) {
+        // x op c = t
+        (inner, Expr::Const(c)) => {
+          target = match op {
+            Op::Add => target.apply(Op::Sub, *c)?,
+            Op::Sub => target.apply(Op::Add, *c)?,
+            Op::Mul => target.apply(Op::Div, *c)?,
+            Op::Div => target.apply(Op::Mul, *c)?,
+          };
+          expr = inner;
+        }
+        // c op x = t
+        (Expr::Const(c), inner) => {
+          target = match op {
+            Op::Add => target.apply(Op::Sub, *c)?,
+            Op::Sub => c.apply(Op::Sub, target)?,
+            Op::Mul => target.apply(Op::Div, *c)?,
+            Op::Div => c.apply(Op::Div, target)?,
+          };
+          expr = inner;
+        }
+        _ => unreachable!("Both sides of an operation have the variable"),
+      },
+      Expr::Const(_) => unreachable!("Counted the variable but didn't find it"),
+    }
+  }
+
+  target.to_integer().ok_or(ExprError::NotIntegral(target))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::day21_expr::{solve_all, solve_for, Comparison, Expr, ExprError, Op, Rational};
+
+  fn c(value: i64) -> Expr {
+    Expr::Const(Rational::integer(value))
+  }
+
+  fn humn() -> Expr {
+    Expr::Var("humn".to_string())
+  }
+
+  fn bin(left: Expr, op: Op, right: Expr) -> Expr {
+    Expr::bin(left, op, right).unwrap()
+  }
+
+  #[test]
+  fn test_rationals() {
+    assert_eq!(Rational::new(6, -4), Rational::new(-3, 2));
+    assert_eq!(Rational::new(6, -4).to_string(), "-3/2");
+    assert_eq!(Rational::new(8, 4).to_integer(), Some(2));
+    assert_eq!(Rational::new(1, 3).to_integer(), None);
+  }
+
+  #[test]
+  fn test_constant_folding() {
+    assert_eq!(bin(c(7), Op::Div, c(2)), Expr::Const(Rational::new(7, 2)));
+    assert_eq!(bin(bin(c(7), Op::Div, c(2)), Op::Mul, c(4)), c(14));
+    assert_eq!(Expr::bin(c(1), Op::Div, c(0)), Err(ExprError::DivisionByZero));
+    assert!(matches!(bin(humn(), Op::Add, c(1)), Expr::BinOp(..)));
+  }
+
+  #[test]
+  fn test_isolate_on_either_side() {
+    // 5 + (2 - humn) = 1
+    let expr = bin(c(5), Op::Add, bin(c(2), Op::Sub, humn()));
+    assert_eq!(solve_for(&expr, &c(1), "humn"), Ok(6));
+    assert_eq!(solve_for(&c(1), &expr, "humn"), Ok(6));
+
+    // 5 - (2 - humn) = 1
+    let expr = bin(c(5), Op::Sub, bin(c(2), Op::Sub, humn()));
+    assert_eq!(solve_for(&expr, &c(1), "humn"), Ok(-2));
+
+    // 5 + (8 / humn) = 1
+    let expr = bin(c(5), Op::Add, bin(c(8), Op::Div, humn()));
+    assert_eq!(solve_for(&expr, &c(1), "humn"), Ok(-2));
+
+    // 4 / (8 / humn) = 1
+    let expr = bin(c(4), Op::Div, bin(c(8), Op::Div, humn()));
+    assert_eq!(solve_for(&expr, &c(1), "humn"), Ok(2));
+
+    // (humn * 3 - 1) / 4 = 2
+    let expr = bin(bin(bin(humn(), Op::Mul, c(3)), Op::Sub, c(1)), Op::Div, c(4));
+    assert_eq!(solve_for(&expr, &c(2), "humn"), Ok(3));
+  }
+
+  #[test]
+  fn test_solve_errors() {
+    let expr = bin(humn(), Op::Mul, c(2));
+    assert_eq!(solve_for(&expr, &c(3), "humn"), Err(ExprError::NotIntegral(Rational::new(3, 2))));
+    assert_eq!(solve_for(&expr, &c(3), "x"), Err(ExprError::VariableMissing("x".to_string())));
+
+    let twice = bin(humn(), Op::Add, humn());
+    assert_eq!(solve_for(&twice, &c(4), "humn"), Err(ExprError::VariableRepeated("humn".to_string(), 2)));
+    assert_eq!(solve_for(&expr, &humn(), "humn").unwrap_err().to_string(), "humn appears 2 times in the equation");
+
+    // 4 / humn = 0 has no solution
+    let expr = bin(c(4), Op::Div, humn());
+    assert_eq!(solve_for(&expr, &c(0), "humn"), Err(ExprError::DivisionByZero));
+  }
+
+  #[test]
+  fn test_unknown_in_several_places() {
+    // humn * humn - 4 * humn = 12 has roots 6 and -2
+    let left = bin(bin(humn(), Op::Mul, humn()), Op::Sub, bin(c(4), Op::Mul, humn()));
+    assert_eq!(solve_all(&left, &c(12), "humn", Comparison::Equal), Ok(vec![-2, 6]));
+    assert_eq!(solve_all(&left, &c(12), "humn", Comparison::DifferenceZero), Ok(vec![-2, 6]));
+
+    // humn + humn / 2 = 9 is linear once reduced
+    let left = bin(humn(), Op::Add, bin(humn(), Op::Div, c(2)));
+    assert_eq!(solve_all(&left, &c(9), "humn", Comparison::Equal), Ok(vec![6]));
+    assert_eq!(solve_all(&left, &c(10), "humn", Comparison::Equal), Err(ExprError::NotIntegral(Rational::new(20, 3))));
+
+    // (humn + 1) / humn = humn / 2 + 1 is humn^2 = 2, which has no whole roots
+    let left = bin(bin(humn(), Op::Add, c(1)), Op::Div, humn());
+    let right = bin(bin(humn(), Op::Div, c(2)), Op::Add, c(1));
+    assert_eq!(solve_all(&left, &right, "humn", Comparison::Equal), Err(ExprError::NoSolution));
+
+    // humn / humn = 1 reduces to 0 = 0
+    let left = bin(humn(), Op::Div, humn());
+    assert_eq!(solve_all(&left, &c(1), "humn", Comparison::Equal), Err(ExprError::AnyValue));
+
+    // humn^3 = 0, and a root at 0 that would divide by zero is thrown away
+    let cube = bin(bin(humn(), Op::Mul, humn()), Op::Mul, humn());
+    assert_eq!(solve_all(&cube, &c(0), "humn", Comparison::Equal), Ok(vec![0]));
+    let left = bin(cube.clone(), Op::Div, humn());
+    assert_eq!(solve_all(&left, &c(0), "humn", Comparison::Equal), Err(ExprError::NoSolution));
+
+    // humn^3 - 8 = 0 only has the one real root
+    let left = bin(cube.clone(), Op::Sub, c(8));
+    assert_eq!(solve_all(&left, &c(0), "humn", Comparison::Equal), Ok(vec![2]));
+
+    let too_high = bin(bin(cube.clone(), Op::Mul, cube), Op::Sub, humn());
+    assert_eq!(solve_all(&too_high, &c(0), "humn", Comparison::Equal), Err(ExprError::DegreeTooHigh(6)));
+  }
+
+  #[test]
+  fn test_roots_at_puzzle_scale() {
+    let root = 350_000_000_001;
+    let square = bin(humn(), Op::Mul, humn());
+    let big = |value: i128| Expr::Const(Rational::new(value, 1));
+    assert_eq!(solve_all(&square, &big(root as i128 * root as i128), "humn", Comparison::Equal), Ok(vec![-root, root]));
+
+    // humn^3 = root^3 has the one real root, and root^3 + 1 is just past a cube
+    let cube = bin(square, Op::Mul, humn());
+    let cubed = root as i128 * root as i128 * root as i128;
+    assert_eq!(solve_all(&cube, &big(cubed), "humn", Comparison::Equal), Ok(vec![root]));
+    assert_eq!(solve_all(&cube, &big(cubed + 1), "humn", Comparison::Equal), Err(ExprError::NoSolution));
+
+    // (humn - 1000003) * (humn + 3) * humn * (humn - 7) has four whole roots,
+    // as big as fit once raised to the fourth power
+    let factor = |k: i64| bin(humn(), Op::Sub, c(k));
+    let quartic = bin(bin(bin(factor(1_000_003), Op::Mul, factor(-3)), Op::Mul, humn()), Op::Mul, factor(7));
+    assert_eq!(solve_all(&quartic, &c(0), "humn", Comparison::Equal), Ok(vec![-3, 0, 7, 1_000_003]));
+  }
+}
@@ -1,31 +1,85 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::day08::Point;
 use crate::day12::lines_to_grid_char_val;
+use crate::day22_cube::CubeNet;
 use crate::utils::read_chunks;
 
 pub fn part_one() -> i64 {
+  let (grid, moves) = read_input();
+
+  let (position, direction) = process_instructions(&grid, &moves, &FlatWrap);
+  let password = compute_password(position, direction);
+
+  password
+}
+
+// The map and the list of moves
+pub fn read_input() -> (HashMap<Point, char>, Vec<Move>) {
   let raw_input = read_chunks("day22.txt", "\n\n");
-  let map_lines = raw_input.get(0).unwrap()
+  let map_lines = raw_input.first().unwrap()
     .split("\n")
     .map(|l| l.to_string())
     .collect::<Vec<String>>();
   let grid = parse_grid(&map_lines);
-  let instructions = parse_instructions(raw_input);
+  let moves = parse_moves(raw_input.get(1).unwrap()).unwrap_or_else(|e| panic!("Invalid moves: {}", e));
+  (grid, moves)
+}
 
-  let (position, direction) = process_instructions(&grid, &instructions, true);
-  let password = compute_password(position, direction);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Move {
+  Forward(i64),
+  TurnLeft,
+  TurnRight,
+}
 
-  password
+impl fmt::Display for Move {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Move::Forward(steps) => write!(f, "{}", steps),
+      Move::TurnLeft => write!(f, "L"),
+      Move::TurnRight => write!(f, "R"),
+    }
+  }
 }
 
-fn parse_instructions(raw_input: Vec<String>) -> Vec<String> {
-  raw_input.get(1).unwrap().trim()
-    .replace("R", " R ")
-    .replace("L", " L ")
-    .split(" ")
-    .map(|s| s.to_string())
-    .collect::<Vec<String>>()
+// A character in the moves that isn't a number, `L` or `R`. `position` is
+// 1-based
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveParseError {
+  pub position: usize,
+  pub found: char,
+}
+
+impl fmt::Display for MoveParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "unexpected '{}' at character {}", self.found, self.position)
+  }
+}
+
+// Moves like `10R5L5`, with any surrounding whitespace ignored
+pub fn parse_moves(text: &str) -> Result<Vec<Move>, MoveParseError> {
+  let mut moves = vec![];
+  let mut steps: Option<i64> = None;
+  for (i, c) in text.trim().chars().enumerate() {
+    if let Some(digit) = c.to_digit(10) {
+      steps = Some(steps.unwrap_or(0) * 10 + digit as i64);
+      continue;
+    }
+    if let Some(steps) = steps.take() {
+      moves.push(Move::Forward(steps));
+    }
+    moves.push(match c {
+      'L' => Move::TurnLeft,
+      'R' => Move::TurnRight,
+      found => return Err(MoveParseError { position: i + 1, found }),
+    });
+  }
+  if let Some(steps) = steps {
+    moves.push(Move::Forward(steps));
+  }
+  Ok(moves)
 }
 
 fn parse_grid(map_lines: &Vec<String>) -> HashMap<Point, char> {
@@ -36,13 +90,157 @@ fn parse_grid(map_lines: &Vec<String>) -> HashMap<Point, char> {
     .collect()
 }
 
-fn process_instructions(grid: &HashMap<Point, char>, instructions: &Vec<String>, part_one: bool) -> (Point, Direction) {
-  let wrap_method = if part_one {
-    wrap_2d
-  } else {
-    wrap_3d
-  };
+fn process_instructions(grid: &HashMap<Point, char>, moves: &[Move], topology: &dyn Topology) -> (Point, Direction) {
+  walk(grid, moves, topology).trail.pop().unwrap()
+}
+
+// Where walking off the map comes back on. `mirrored` is set when the surface
+// is flipped over on the far side, so the walker's left is now the map's right
+pub struct Crossing {
+  pub position: Point,
+  pub direction: Direction,
+  pub mirrored: bool,
+}
+
+// How the edges of the map join up, which is all that changes between the
+// different surfaces the map can be walked on
+pub trait Topology {
+  // Coming back on where we left means there's nowhere to go
+  fn cross_edge(&self, grid: &HashMap<Point, char>, position: &Point, direction: &Direction) -> Crossing;
+
+  // Which part of the surface a tile is on, for describing crossings
+  fn face_of(&self, _position: &Point) -> Option<usize> {
+    None
+  }
+}
+
+// Part one: each row and column wraps round on itself
+pub struct FlatWrap;
+
+impl Topology for FlatWrap {
+  fn cross_edge(&self, grid: &HashMap<Point, char>, position: &Point, direction: &Direction) -> Crossing {
+    let (position, direction) = wrap_2d(grid, position, direction);
+    Crossing { position, direction, mirrored: false }
+  }
+}
+
+fn bounds(grid: &HashMap<Point, char>) -> (i64, i64) {
+  (grid.keys().map(|p| p.x).max().unwrap() + 1, grid.keys().map(|p| p.y).max().unwrap() + 1)
+}
+
+// The rectangle around the map with opposite sides joined, so walking off one
+// side comes back on the other. Unlike `FlatWrap` gaps in the middle of a row
+// are stepped over rather than wrapped at
+pub struct Torus {
+  width: i64,
+  height: i64,
+}
+
+impl Torus {
+  pub fn new(grid: &HashMap<Point, char>) -> Torus {
+    let (width, height) = bounds(grid);
+    Torus { width, height }
+  }
+}
+
+impl Topology for Torus {
+  fn cross_edge(&self, grid: &HashMap<Point, char>, position: &Point, direction: &Direction) -> Crossing {
+    let mut next = *position;
+    loop {
+      next = next + direction.step_point();
+      next = Point { x: next.x.rem_euclid(self.width), y: next.y.rem_euclid(self.height) };
+      if grid.contains_key(&next) {
+        return Crossing { position: next, direction: direction.clone(), mirrored: false };
+      }
+    }
+  }
+}
+
+// The rectangle around the map with its left and right sides joined after a
+// half twist, so going off the right of a row comes back on the left of the
+// row that's as far from the bottom as it was from the top. The top and bottom
+// are the edge of the strip, and can't be walked off
+pub struct Mobius {
+  width: i64,
+  height: i64,
+}
+
+impl Mobius {
+  pub fn new(grid: &HashMap<Point, char>) -> Mobius {
+    let (width, height) = bounds(grid);
+    Mobius { width, height }
+  }
+}
+
+impl Topology for Mobius {
+  fn cross_edge(&self, grid: &HashMap<Point, char>, position: &Point, direction: &Direction) -> Crossing {
+    let mut next = *position;
+    let mut mirrored = false;
+    loop {
+      next = next + direction.step_point();
+      if next.y < 0 || next.y >= self.height {
+        return Crossing { position: *position, direction: direction.clone(), mirrored: false };
+      }
+      if next.x < 0 || next.x >= self.width {
+        next = Point { x: next.x.rem_euclid(self.width), y: self.height - 1 - next.y };
+        mirrored = !mirrored;
+      }
+      if grid.contains_key(&next) {
+        return Crossing { position: next, direction: direction.clone(), mirrored };
+      }
+    }
+  }
+}
+
+impl Topology for CubeNet {
+  fn cross_edge(&self, _grid: &HashMap<Point, char>, position: &Point, direction: &Direction) -> Crossing {
+    let (position, direction) = self.wrap(position, direction);
+    Crossing { position, direction, mirrored: false }
+  }
+
+  fn face_of(&self, position: &Point) -> Option<usize> {
+    Some(CubeNet::face_of(self, position))
+  }
+}
+
+// Picks a surface to walk on by name: flat, cube, torus or mobius
+pub fn topology(name: &str, grid: &HashMap<Point, char>) -> Box<dyn Topology> {
+  match name {
+    "flat" => Box::new(FlatWrap),
+    "cube" => Box::new(CubeNet::fold(grid).unwrap_or_else(|e| panic!("Unable to fold the map into a cube: {}", e))),
+    "torus" => Box::new(Torus::new(grid)),
+    "mobius" => Box::new(Mobius::new(grid)),
+    other => panic!("Unknown topology: {}", other),
+  }
+}
 
+// Walking off the map somewhere and where it came back on. `faces` are the
+// parts of the surface either side of the edge crossed, such as cube faces
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wrap {
+  pub from: (Point, Direction),
+  pub to: (Point, Direction),
+  pub faces: Option<(usize, usize)>,
+}
+
+// What one move did. `blocked` is set when a wall stopped it early
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayStep {
+  pub instruction: Move,
+  pub start: (Point, Direction),
+  pub end: (Point, Direction),
+  pub wraps: Vec<Wrap>,
+  pub blocked: bool,
+}
+
+// Every tile visited with the way we were facing, including turning on the
+// spot, along with what each move did
+pub struct Walk {
+  pub trail: Vec<(Point, Direction)>,
+  pub replay: Vec<ReplayStep>,
+}
+
+pub fn walk(grid: &HashMap<Point, char>, moves: &[Move], topology: &dyn Topology) -> Walk {
   let starting_y = 0;
   let starting_x = grid.keys()
     .filter(|p| p.y == starting_y)
@@ -51,51 +249,107 @@ fn process_instructions(grid: &HashMap<Point, char>, instructions: &Vec<String>,
 
   let mut position = Point { x: starting_x, y: starting_y };
   let mut direction = Direction::Right;
-
-  for instruction in instructions {
-
-    match instruction.parse::<i64>() {
-      Ok(steps) => {
-        for _ in 0..steps {
-          let (new_position, new_direction) = step(grid, &position, &direction, wrap_method);
-          position = new_position;
-          direction = new_direction;
+  // Whether we've been flipped over, so turns go the other way on the map
+  let mut mirrored = false;
+  let mut trail = vec![(position, direction.clone())];
+  let mut replay = vec![];
+
+  for instruction in moves {
+    let start = (position, direction.clone());
+    let mut wraps = vec![];
+    let mut blocked = false;
+
+    match instruction {
+      Move::Forward(steps) => {
+        for _ in 0..*steps {
+          let (crossing, wrapped) = step(grid, &position, &direction, topology);
+          if crossing.position == position {
+            blocked = true;
+            break;
+          }
+          if wrapped {
+            wraps.push(Wrap {
+              from: (position, direction.clone()),
+              to: (crossing.position, crossing.direction.clone()),
+              faces: topology.face_of(&position).zip(topology.face_of(&crossing.position)),
+            });
+          }
+          position = crossing.position;
+          direction = crossing.direction;
+          mirrored ^= crossing.mirrored;
+          trail.push((position, direction.clone()));
         }
       }
-      Err(_) => {
-        direction = match instruction.as_str() {
-          "R" => direction.turn_right(),
-          "L" => direction.turn_left(),
-          _ => panic!("unknown direction")
-        }
+      Move::TurnLeft | Move::TurnRight => {
+        direction = match (instruction, mirrored) {
+          (Move::TurnRight, false) | (Move::TurnLeft, true) => direction.turn_right(),
+          _ => direction.turn_left(),
+        };
+        trail.push((position, direction.clone()));
       }
     }
 
+    replay.push(ReplayStep { instruction: *instruction, start, end: (position, direction.clone()), wraps, blocked });
   }
 
-  (position, direction)
+  Walk { trail, replay }
+}
+
+// The map with the trail drawn over it the way the puzzle does, showing the
+// last way we were facing on each tile
+pub fn render_trail(grid: &HashMap<Point, char>, trail: &[(Point, Direction)]) -> String {
+  let facing: HashMap<Point, char> = trail.iter().map(|(p, d)| (*p, d.arrow())).collect();
+  let width = grid.keys().map(|p| p.x).max().unwrap_or(-1) + 1;
+  let height = grid.keys().map(|p| p.y).max().unwrap_or(-1) + 1;
+
+  let mut text = String::new();
+  for y in 0..height {
+    let mut line = String::new();
+    for x in 0..width {
+      let p = Point { x, y };
+      line.push(*facing.get(&p).or_else(|| grid.get(&p)).unwrap_or(&' '));
+    }
+    text.push_str(line.trim_end());
+    text.push('\n');
+  }
+  text
+}
+
+// One line per instruction, then a line for each time it wrapped
+pub fn replay_to_text(replay: &[ReplayStep]) -> String {
+  let describe = |(p, d): &(Point, Direction)| format!("{},{} {}", p.x, p.y, d.arrow());
+  let mut text = String::new();
+  for step in replay {
+    let blocked = if step.blocked { " (blocked)" } else { "" };
+    text.push_str(&format!("{:>4}: {} -> {}{}\n", step.instruction.to_string(), describe(&step.start), describe(&step.end), blocked));
+    for wrap in &step.wraps {
+      let edge = match wrap.faces {
+        Some((from, to)) => format!(" over the edge from face {} to face {}", from, to),
+        None => String::new(),
+      };
+      text.push_str(&format!("      wrapped {} -> {}{}\n", describe(&wrap.from), describe(&wrap.to), edge));
+    }
+  }
+  text
 }
 
-fn step<T>(
-  grid: &HashMap<Point, char>,
-  position: &Point,
-  direction: &Direction,
-  wrap: T,
-) -> (Point, Direction)
-  where T: Fn(&HashMap<Point, char>, &Point, &Direction) -> (Point, Direction) {
+// Where moving one tile goes, and whether it had to wrap to get there. Walls
+// leave us where we are
+fn step(grid: &HashMap<Point, char>, position: &Point, direction: &Direction, topology: &dyn Topology) -> (Crossing, bool) {
+  let stay = Crossing { position: *position, direction: direction.clone(), mirrored: false };
   let maybe_simple_next_position = *position + direction.step_point();
   if grid.contains_key(&maybe_simple_next_position) {
     if grid.get(&maybe_simple_next_position).unwrap() == &'#' {
-      (position.clone(), direction.clone())
+      (stay, false)
     } else {
-      (maybe_simple_next_position, direction.clone())
+      (Crossing { position: maybe_simple_next_position, direction: direction.clone(), mirrored: false }, false)
     }
   } else {
-    let (maybe_wrap_next_position, maybe_new_direction) = wrap(grid, position, direction);
-    if grid.get(&maybe_wrap_next_position).unwrap() == &'#' {
-      (position.clone(), direction.clone())
+    let crossing = topology.cross_edge(grid, position, direction);
+    if grid.get(&crossing.position).unwrap() == &'#' {
+      (stay, false)
     } else {
-      (maybe_wrap_next_position, maybe_new_direction)
+      (crossing, true)
     }
   }
 }
@@ -111,124 +365,13 @@ fn wrap_2d(grid: &HashMap<Point, char>, position: &Point, direction: &Direction)
   (new_position, direction.clone())
 }
 
-fn wrap_3d(_grid: &HashMap<Point, char>, position: &Point, direction: &Direction) -> (Point, Direction) {
-  // println!("Start: {:?}, {:?}", position, direction);
-  let default_new_position = Point { x: -1, y: -1 };
-  let mut new_position = default_new_position.clone();
-  let mut new_direction = Direction::Up;
-
-  match direction {
-    Direction::Up => {
-      if position.y == 0 && position.x >= 50 && position.x < 100 {
-        // i
-        new_direction = Direction::Right;
-        new_position = Point { x: 0, y: position.x + 100 };
-      } else if position.y == 0 && position.x >= 100 && position.x < 150 {
-        // h
-        new_direction = Direction::Up;
-        new_position = Point { x: position.x - 100, y: 199 }
-      } else if position.y == 100 && position.x >= 0 && position.x < 50 {
-        // d
-        new_direction = Direction::Right;
-        new_position = Point { x: 50, y: position.x + 50 }
-      }
-    }
-    Direction::Down => {
-      if position.y == 199 && position.x >= 0 && position.x < 50 {
-        // h
-        new_direction = Direction::Down;
-        new_position = Point { x: position.x + 100, y: 0 }
-      } else if position.y == 149 && position.x >= 50 && position.x < 100 {
-        // g
-        new_direction = Direction::Left;
-        new_position = Point { x: 49, y: position.x + 100 }
-      } else if position.y == 49 && position.x >= 100 && position.x < 150 {
-        // b
-        new_direction = Direction::Left;
-        new_position = Point { x: 99, y: position.x - 50 }
-      }
-    }
-    Direction::Left => {
-      if position.x == 50 && position.y >= 0 && position.y < 50 {
-        // k
-        new_direction = Direction::Right;
-        // y = 0, y = 149
-        // y = 49, y = 100
-        // 100 + (49 - y)
-        new_position = Point { x: 0, y: 100 + (49 - position.y) }
-      } else if position.x == 50 && position.y >= 50 && position.y < 100 {
-        // d
-        new_direction = Direction::Down;
-        // y = 50, x = 0
-        // y = 100, x = 50
-        new_position = Point { x: position.y - 50, y: 100 }
-      } else if position.x == 0 && position.y >= 100 && position.y < 150 {
-        // k
-        new_direction = Direction::Right;
-        //  y = 100, y = 49
-        // y = 101, y = 48
-        // 148, 1
-        // y = 149, y = 0
-        // y - 100 + 49
-
-        // 49 - (y - 100)
-        new_position = Point { x: 50, y: 49 - (position.y - 100) }
-      } else if position.x == 0 && position.y >= 150 && position.y < 200 {
-        // i
-        new_direction = Direction::Down;
-        // y = 150, x = 50
-        // y = 199, x = 99
-        new_position = Point { x: position.y - 100, y: 0 }
-      }
-    }
-    Direction::Right => {
-      if position.x == 149 && position.y >= 0 && position.y < 50 {
-        // L
-        new_direction = Direction::Left;
-        // y = 0, y = 149
-        // y = 49, y = 100
-        // 100 + (49 - y)
-        new_position = Point { x: 99, y: 100 + (49 - position.y) }
-      } else if position.x == 99 && position.y >= 50 && position.y < 100 {
-        // b
-        new_direction = Direction::Up;
-        // y = 50, x = 100
-        // y = 99, x = 149
-        new_position = Point { x: position.y + 50, y: 49 }
-      } else if position.x == 99 && position.y >= 100 && position.y < 150 {
-        // L
-        new_direction = Direction::Left;
-        // y = 100, y = 49
-        // y = 149, y = 0
-        // 49 - (y - 100)
-        new_position = Point { x: 149, y: 49 - (position.y - 100) }
-      } else if position.x == 49 && position.y >= 150 && position.y < 200 {
-        // G
-        new_direction = Direction::Up;
-        // y = 150, x = 50
-        // y = 199, x = 99
-        // y - 100
-        new_position = Point { x: position.y - 100, y: 149 }
-      }
-    }
-  };
-
-  if new_position == default_new_position {
-    panic!("never figured out where to go, uh oh!")
-  }
-
-  // println!("End: {:?}, {:?}", new_position, new_direction);
-  // println!();
-  (new_position, new_direction)
-}
-
-fn compute_password(position: Point, direction: Direction) -> i64 {
+pub fn compute_password(position: Point, direction: Direction) -> i64 {
   // Puzzle grid is 1-based, not 0-based
   (1000 * (1 + position.y)) + (4 * (1 + position.x)) + direction.value()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum Direction {
+pub enum Direction {
   Up,
   Down,
   Left,
@@ -245,7 +388,7 @@ impl Direction {
     }
   }
 
-  fn turn_right(&self) -> Direction {
+  pub fn turn_right(&self) -> Direction {
     match self {
       Direction::Up => Direction::Right,
       Direction::Down => Direction::Left,
@@ -254,7 +397,7 @@ impl Direction {
     }
   }
 
-  fn turn_left(&self) -> Direction {
+  pub fn turn_left(&self) -> Direction {
     match self {
       Direction::Up => Direction::Left,
       Direction::Down => Direction::Right,
@@ -263,7 +406,16 @@ impl Direction {
     }
   }
 
-  fn step_point(&self) -> Point {
+  pub fn arrow(&self) -> char {
+    match self {
+      Direction::Up => '^',
+      Direction::Down => 'v',
+      Direction::Left => '<',
+      Direction::Right => '>',
+    }
+  }
+
+  pub fn step_point(&self) -> Point {
     match self {
       Direction::Up => Point { x: 0, y: -1 },
       Direction::Down => Point { x: 0, y: 1 },
@@ -274,15 +426,10 @@ impl Direction {
 }
 
 pub fn part_two() -> i64 {
-  let raw_input = read_chunks("day22.txt", "\n\n");
-  let map_lines = raw_input.get(0).unwrap()
-    .split("\n")
-    .map(|l| l.to_string())
-    .collect::<Vec<String>>();
-  let grid = parse_grid(&map_lines);
-  let instructions = parse_instructions(raw_input);
+  let (grid, moves) = read_input();
+  let cube = CubeNet::fold(&grid).unwrap_or_else(|e| panic!("Unable to fold the map into a cube: {}", e));
 
-  let (position, direction) = process_instructions(&grid, &instructions, false);
+  let (position, direction) = process_instructions(&grid, &moves, &cube);
   let password = compute_password(position, direction);
 
   password
@@ -292,236 +439,132 @@ pub fn part_two() -> i64 {
 #[cfg(test)]
 mod tests {
   use crate::day08::Point;
-  use crate::day22::{compute_password, Direction, parse_grid, process_instructions, wrap_3d};
-  use crate::utils::read_chunks;
+  use crate::day22::{compute_password, Direction, FlatWrap, Mobius, Move, MoveParseError, parse_grid, parse_moves, process_instructions, render_trail, replay_to_text, Torus, walk};
+  use crate::day22_cube::CubeNet;
 
   #[test]
   fn test_part_1() {
     let map_data = get_part_1_map_data();
     let instructions = get_part_1_instructions();
     let grid = parse_grid(&map_data);
-    let (position, direction) = process_instructions(&grid, &instructions, true);
+    let (position, direction) = process_instructions(&grid, &instructions, &FlatWrap);
     let password = compute_password(position, direction);
 
     assert_eq!(password, 6032);
   }
 
-  // Never wrote code that would work for the example data
-  // #[test]
-  // fn test_part_2() {
-  //   let map_data = get_part_1_map_data();
-  //   let instructions = get_part_1_instructions();
-  //   let grid = parse_grid(&map_data);
-  //   let (position, direction) = process_instructions(&grid, &instructions, false);
-  //   let password = compute_password(position, direction);
-  //
-  //   assert_eq!(password, 5031);
-  // }
+  #[test]
+  fn test_part_2() {
+    let map_data = get_part_1_map_data();
+    let instructions = get_part_1_instructions();
+    let grid = parse_grid(&map_data);
+    let (position, direction) = process_instructions(&grid, &instructions, &CubeNet::fold(&grid).unwrap());
+    let password = compute_password(position, direction);
+
+    assert_eq!(password, 5031);
+  }
 
   #[test]
-  fn test_compute_password() {
-    let password = compute_password(Point { x: 7, y: 5 }, Direction::Right);
-    assert_eq!(password, 6032)
+  fn test_trail_overlay() {
+    let grid = parse_grid(&get_part_1_map_data());
+    let walked = walk(&grid, &get_part_1_instructions(), &FlatWrap);
+    let overlay = render_trail(&grid, &walked.trail);
+    let expected = [
+      "        >>v#",
+      "        .#v.",
+      "        #.v.",
+      "        ..v.",
+      "...#...v..v#",
+      ">>>v...>#.>>",
+      "..#v...#....",
+      "...>>>>v..#.",
+      "        ...#....",
+      "        .....#..",
+      "        .#......",
+      "        ......#.",
+    ];
+    // The sample map has letters marking some tiles, which aren't part of the
+    // puzzle's drawing
+    let overlay: Vec<String> = overlay.lines().map(|l| l.replace(['A', 'B', 'C', 'D'], ".")).collect();
+    assert_eq!(overlay, expected);
   }
 
   #[test]
-  fn verify_3d_wrapping() {
-    let raw_input = read_chunks("day22.txt", "\n\n");
-    let map_lines = raw_input.get(0).unwrap()
-      .split("\n")
-      .map(|l| l.to_string())
-      .collect::<Vec<String>>();
-    let grid = parse_grid(&map_lines);
-    let _instructions = raw_input.get(1).unwrap().trim()
-      .replace("R", " R ")
-      .replace("L", " L ")
-      .split(" ")
-      .map(|s| s.to_string())
-      .collect::<Vec<String>>();
-
-    // top I
-    let p = Point {x: 50, y: 0};
-    let d = Direction::Up;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 0, y: 150});
-    assert_eq!(dd, Direction::Right);
-
-    let p = Point {x: 99, y: 0};
-    let d = Direction::Up;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 0, y: 199});
-    assert_eq!(dd, Direction::Right);
-
-    // top H
-    let p = Point {x: 100, y: 0};
-    let d = Direction::Up;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 0, y: 199});
-    assert_eq!(dd, Direction::Up);
-
-    let p = Point {x: 149, y: 0};
-    let d = Direction::Up;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 49, y: 199});
-    assert_eq!(dd, Direction::Up);
-
-    // rightmost L
-    let p = Point {x: 149, y: 0};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 99, y: 149});
-    assert_eq!(dd, Direction::Left);
-
-    let p = Point {x: 149, y: 49};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 99, y: 100});
-    assert_eq!(dd, Direction::Left);
-
-    // upper B
-    let p = Point {x: 100, y: 49};
-    let d = Direction::Down;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 99, y: 50});
-    assert_eq!(dd, Direction::Left);
-
-    let p = Point {x: 149, y: 49};
-    let d = Direction::Down;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 99, y: 99});
-    assert_eq!(dd, Direction::Left);
-
-    // middle B
-    let p = Point {x: 99, y: 50};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 100, y: 49});
-    assert_eq!(dd, Direction::Up);
-
-    let p = Point {x: 99, y: 99};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 149, y: 49});
-    assert_eq!(dd, Direction::Up);
-
-    // middle L
-    let p = Point {x: 99, y: 100};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 149, y: 49});
-    assert_eq!(dd, Direction::Left);
-
-    let p = Point {x: 99, y: 149};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 149, y: 0});
-    assert_eq!(dd, Direction::Left);
-
-    // middle G
-    let p = Point {x: 50, y: 149};
-    let d = Direction::Down;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 49, y: 150});
-    assert_eq!(dd, Direction::Left);
-
-    let p = Point {x: 99, y: 149};
-    let d = Direction::Down;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 49, y: 199});
-    assert_eq!(dd, Direction::Left);
-
-    // bottom G
-    let p = Point {x: 49, y: 150};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 50, y: 149});
-    assert_eq!(dd, Direction::Up);
-
-    let p = Point {x: 49, y: 199};
-    let d = Direction::Right;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 99, y: 149});
-    assert_eq!(dd, Direction::Up);
-
-    // bottom H
-    let p = Point {x: 0, y: 199};
-    let d = Direction::Down;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 100, y: 0});
-    assert_eq!(dd, Direction::Down);
-
-    let p = Point {x: 49, y: 199};
-    let d = Direction::Down;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 149, y: 0});
-    assert_eq!(dd, Direction::Down);
-
-    // bottom left I
-    let p = Point {x: 0, y: 150};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 50, y: 0});
-    assert_eq!(dd, Direction::Down);
-
-    let p = Point {x: 0, y: 199};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 99, y: 0});
-    assert_eq!(dd, Direction::Down);
-
-    // left bottom K
-    let p = Point {x: 0, y: 100};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 50, y: 49});
-    assert_eq!(dd, Direction::Right);
-
-    let p = Point {x: 0, y: 149};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 50, y: 0});
-    assert_eq!(dd, Direction::Right);
-
-    // left D
-    let p = Point {x: 0, y: 100};
-    let d = Direction::Up;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 50, y: 50});
-    assert_eq!(dd, Direction::Right);
-
-    let p = Point {x: 49, y: 100};
-    let d = Direction::Up;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 50, y: 99});
-    assert_eq!(dd, Direction::Right);
-
-    // middle D
-    let p = Point {x: 50, y: 50};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 0, y: 100});
-    assert_eq!(dd, Direction::Down);
-
-    let p = Point {x: 50, y: 99};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 49, y: 100});
-    assert_eq!(dd, Direction::Down);
-
-    // top K
-    let p = Point {x: 50, y: 0};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 0, y: 149});
-    assert_eq!(dd, Direction::Right);
-
-    let p = Point {x: 50, y: 49};
-    let d = Direction::Left;
-    let (pp, dd) = wrap_3d(&grid, &p, &d);
-    assert_eq!(pp, Point {x: 0, y: 100});
-    assert_eq!(dd, Direction::Right);
+  fn test_replay_flags_wraps() {
+    let grid = parse_grid(&get_part_1_map_data());
+    let walked = walk(&grid, &get_part_1_instructions(), &CubeNet::fold(&grid).unwrap());
+    assert_eq!(walked.replay.len(), 13);
+    assert_eq!(walked.trail.last(), Some(&(walked.replay[12].end.0, walked.replay[12].end.1.clone())));
+
+    // "10" stops at a wall after two steps
+    assert!(walked.replay[0].blocked);
+    assert!(walked.replay[0].wraps.is_empty());
+
+    // The third move walks off the right of face 4 onto face 6, facing down,
+    // then hits a wall
+    assert!(walked.replay[4].blocked);
+    let wrap = &walked.replay[4].wraps[0];
+    assert_eq!(wrap.from.0, Point { x: 11, y: 5 });
+    assert_eq!(wrap.to.0, Point { x: 14, y: 8 });
+    assert_eq!(wrap.faces, Some((4, 6)));
+    assert_eq!(wrap.to.1, Direction::Down);
+
+    let text = replay_to_text(&walked.replay);
+    assert!(text.starts_with("  10: 8,0 > -> 10,0 > (blocked)\n"));
+    assert!(text.contains("over the edge from face 4 to face 6"));
   }
 
+  #[test]
+  fn test_parse_moves() {
+    assert_eq!(parse_moves("10R5L\n"), Ok(vec![Move::Forward(10), Move::TurnRight, Move::Forward(5), Move::TurnLeft]));
+    assert_eq!(parse_moves(""), Ok(vec![]));
+    assert_eq!(parse_moves("10R5X3"), Err(MoveParseError { position: 5, found: 'X' }));
+    assert_eq!(get_part_1_instructions().iter().map(|m| m.to_string()).collect::<String>(), "10R5L5R10L4R5L5");
+  }
+
+  #[test]
+  fn test_torus_steps_over_gaps() {
+    let grid = parse_grid(&vec![". .".to_string(), "...".to_string()]);
+    let moves = parse_moves("1").unwrap();
+
+    // Flat wrapping only comes back at the far end of the row, which is where
+    // we started
+    let flat = walk(&grid, &moves, &FlatWrap);
+    assert_eq!(flat.trail.last().unwrap().0, Point { x: 0, y: 0 });
+    assert!(flat.replay[0].blocked);
+
+    let torus = walk(&grid, &moves, &Torus::new(&grid));
+    assert_eq!(torus.trail.last().unwrap().0, Point { x: 2, y: 0 });
+    assert_eq!(torus.replay[0].wraps.len(), 1);
+
+    // Going up from the top row comes in at the bottom
+    let torus = walk(&grid, &parse_moves("L1").unwrap(), &Torus::new(&grid));
+    assert_eq!(torus.trail.last().unwrap(), &(Point { x: 0, y: 1 }, Direction::Up));
+  }
+
+  #[test]
+  fn test_mobius_flips_rows_and_turns() {
+    let grid = parse_grid(&vec!["...".to_string(), "...".to_string()]);
+    let mobius = Mobius::new(&grid);
+
+    // Off the right of the top row and back on the left of the bottom one,
+    // after which a left turn points down the map
+    let walked = walk(&grid, &parse_moves("4L").unwrap(), &mobius);
+    assert_eq!(walked.replay[0].end, (Point { x: 1, y: 1 }, Direction::Right));
+    assert_eq!(walked.replay[0].wraps[0].to.0, Point { x: 0, y: 1 });
+    assert_eq!(walked.replay[1].end.1, Direction::Down);
+
+    // The bottom is the edge of the strip
+    let walked = walk(&grid, &parse_moves("4L1").unwrap(), &mobius);
+    assert!(walked.replay[2].blocked);
+    assert_eq!(walked.trail.last().unwrap().0, Point { x: 1, y: 1 });
+  }
+
+  #[test]
+  fn test_compute_password() {
+    let password = compute_password(Point { x: 7, y: 5 }, Direction::Right);
+    assert_eq!(password, 6032)
+  }
 
   fn get_part_1_map_data() -> Vec<String> {
     vec![
@@ -540,21 +583,7 @@ mod tests {
     ]
   }
 
-  fn get_part_1_instructions() -> Vec<String> {
-    vec![
-      "10".to_string(),
-      "R".to_string(),
-      "5".to_string(),
-      "L".to_string(),
-      "5".to_string(),
-      "R".to_string(),
-      "10".to_string(),
-      "L".to_string(),
-      "4".to_string(),
-      "R".to_string(),
-      "5".to_string(),
-      "L".to_string(),
-      "5".to_string(),
-    ]
+  fn get_part_1_instructions() -> Vec<Move> {
+    parse_moves("10R5L5R10L4R5L5").unwrap()
   }
 }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::day08::Point;
+use crate::day22::Direction;
+
+type Vector = [i64; 3];
+
+fn add(a: Vector, b: Vector) -> Vector {
+  [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: Vector, by: i64) -> Vector {
+  [a[0] * by, a[1] * by, a[2] * by]
+}
+
+fn dot(a: Vector, b: Vector) -> i64 {
+  a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// Where one face of the net ends up once it's folded. `right` and `down` are
+// the map's x and y directions on that face, and `normal` points out of the
+// cube
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Frame {
+  right: Vector,
+  down: Vector,
+  normal: Vector,
+}
+
+impl Frame {
+  // Folding over an edge tips the new face down so that what was pointing out
+  // of the cube now points back across the old face
+  fn fold(&self, direction: &Direction) -> Frame {
+    let Frame { right, down, normal } = *self;
+    match direction {
+      Direction::Right => Frame { right: scale(normal, -1), down, normal: right },
+      Direction::Left => Frame { right: normal, down, normal: scale(right, -1) },
+      Direction::Down => Frame { right, down: scale(normal, -1), normal: down },
+      Direction::Up => Frame { right, down: normal, normal: scale(down, -1) },
+    }
+  }
+
+  fn vector(&self, direction: &Direction) -> Vector {
+    match direction {
+      Direction::Right => self.right,
+      Direction::Left => scale(self.right, -1),
+      Direction::Down => self.down,
+      Direction::Up => scale(self.down, -1),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetError {
+  WrongTileCount(usize),
+  IncompleteFace(Point),
+  NotACube,
+}
+
+impl fmt::Display for NetError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      NetError::WrongTileCount(tiles) => write!(f, "{} tiles can't be split into six square faces", tiles),
+      NetError::IncompleteFace(corner) => write!(f, "the face with its corner at {},{} has gaps in it", corner.x, corner.y),
+      NetError::NotACube => write!(f, "the faces don't fold up into a cube"),
+    }
+  }
+}
+
+// The map folded up into a cube. Faces are numbered 1 to 6 in reading order and
+// identified by their position in the net, in face sized steps
+pub struct CubeNet {
+  pub size: i64,
+  faces: Vec<(Point, Frame)>,
+}
+
+impl CubeNet {
+  // Works out the face size from the number of tiles, then walks the net
+  // folding each face over the edge it shares with one that's already placed.
+  // Any of the 11 nets ends up with a face pointing each way out of the cube
+  pub fn fold(grid: &HashMap<Point, char>) -> Result<CubeNet, NetError> {
+    let size = (1..)
+      .map(|s: i64| (s, 6 * s * s))
+      .find(|(_, tiles)| *tiles >= grid.len() as i64)
+      .filter(|(_, tiles)| *tiles == grid.len() as i64)
+      .map(|(s, _)| s)
+      .ok_or(NetError::WrongTileCount(grid.len()))?;
+
+    let mut corners: Vec<Point> = grid
+      .keys()
+      .filter(|p| p.x % size == 0 && p.y % size == 0)
+      .map(|p| Point { x: p.x / size, y: p.y / size })
+      .collect();
+    corners.sort_by_key(|p| (p.y, p.x));
+    for face in &corners {
+      let complete = (0..size).all(|y| (0..size).all(|x| grid.contains_key(&Point { x: face.x * size + x, y: face.y * size + y })));
+      if !complete {
+        return Err(NetError::IncompleteFace(Point { x: face.x * size, y: face.y * size }));
+      }
+    }
+    if corners.len() != 6 {
+      return Err(NetError::NotACube);
+    }
+
+    let mut frames: HashMap<Point, Frame> = HashMap::new();
+    frames.insert(corners[0], Frame { right: [1, 0, 0], down: [0, 1, 0], normal: [0, 0, -1] });
+    let mut queue = vec![corners[0]];
+    while let Some(face) = queue.pop() {
+      for direction in [Direction::Right, Direction::Down, Direction::Left, Direction::Up] {
+        let next = face + direction.step_point();
+        if corners.contains(&next) && !frames.contains_key(&next) {
+          frames.insert(next, frames[&face].fold(&direction));
+          queue.push(next);
+        }
+      }
+    }
+
+    let faces: Vec<(Point, Frame)> = corners.iter().map(|c| (*c, frames[c])).collect();
+    let mut normals: Vec<Vector> = faces.iter().map(|(_, f)| f.normal).collect();
+    normals.sort();
+    normals.dedup();
+    if normals.len() != 6 {
+      return Err(NetError::NotACube);
+    }
+
+    Ok(CubeNet { size, faces })
+  }
+
+  // Which face, numbered from 1, a tile is on
+  pub fn face_of(&self, position: &Point) -> usize {
+    let corner = Point { x: position.x.div_euclid(self.size), y: position.y.div_euclid(self.size) };
+    self.faces.iter().position(|(c, _)| *c == corner).expect("Position isn't on the cube") + 1
+  }
+
+  // Where walking off the edge of a face in `direction` comes out, and which
+  // way we're facing once we're there. Works on tile centres in 3d, doubled so
+  // they're whole numbers, on a cube centred on the origin
+  pub fn wrap(&self, position: &Point, direction: &Direction) -> (Point, Direction) {
+    let (corner, frame) = self.faces[self.face_of(position) - 1];
+    let size = self.size;
+    let u = position.x - corner.x * size;
+    let v = position.y - corner.y * size;
+    let centre = add(
+      scale(frame.normal, size),
+      add(scale(frame.right, 2 * u + 1 - size), scale(frame.down, 2 * v + 1 - size)),
+    );
+
+    // Step onto the edge then down over it onto the face we were heading for
+    let travel = frame.vector(direction);
+    let landed = add(centre, add(travel, scale(frame.normal, -1)));
+    let (new_corner, new_frame) = *self
+      .faces
+      .iter()
+      .find(|(_, f)| f.normal == travel)
+      .expect("Every direction has a face");
+
+    let offset = add(landed, scale(new_frame.normal, -size));
+    let new_u = (dot(offset, new_frame.right) + size - 1) / 2;
+    let new_v = (dot(offset, new_frame.down) + size - 1) / 2;
+    let new_position = Point { x: new_corner.x * size + new_u, y: new_corner.y * size + new_v };
+
+    let heading = scale(frame.normal, -1);
+    let new_direction = [Direction::Right, Direction::Down, Direction::Left, Direction::Up]
+      .into_iter()
+      .find(|d| new_frame.vector(d) == heading)
+      .unwrap();
+
+    (new_position, new_direction)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use crate::day08::Point;
+  use crate::day22::Direction;
+  use crate::day22_cube::{CubeNet, NetError};
+
+  // An empty map with faces at the given net positions
+  fn net(size: i64, faces: &[(i64, i64)]) -> HashMap<Point, char> {
+    let mut grid = HashMap::new();
+    for (fx, fy) in faces {
+      for y in 0..size {
+        for x in 0..size {
+          grid.insert(Point { x: fx * size + x, y: fy * size + y }, '.');
+        }
+      }
+    }
+    grid
+  }
+
+  // Every one of the 11 cube nets, as rows of the net
+  fn all_nets() -> Vec<Vec<(i64, i64)>> {
+    let layouts = [
+      // 1-4-1
+      [" #  ", "####", " #  "], ["#   ", "####", "#   "], ["#   ", "####", " #  "],
+      ["#   ", "####", "  # "], ["#   ", "####", "   #"], [" #  ", "####", "  # "],
+      // 2-3-1
+      ["##  ", " ###", " #  "], ["##  ", " ###", "  # "], ["##  ", " ###", "   #"],
+      // 2-2-2 and 3-3
+      ["##  ", " ## ", "  ##"], ["### ", "  ###", "    "],
+    ];
+    layouts
+      .iter()
+      .map(|rows| {
+        rows
+          .iter()
+          .enumerate()
+          .flat_map(|(y, row)| row.chars().enumerate().filter(|(_, c)| *c == '#').map(move |(x, _)| (x as i64, y as i64)))
+          .collect()
+      })
+      .collect()
+  }
+
+  fn reverse(direction: &Direction) -> Direction {
+    direction.turn_right().turn_right()
+  }
+
+  #[test]
+  fn test_every_net_folds_and_wraps_back() {
+    for faces in all_nets() {
+      let grid = net(3, &faces);
+      let cube = CubeNet::fold(&grid).unwrap();
+      assert_eq!(cube.size, 3);
+
+      let mut edges = 0;
+      for position in grid.keys() {
+        for direction in [Direction::Right, Direction::Down, Direction::Left, Direction::Up] {
+          if grid.contains_key(&(*position + direction.step_point())) {
+            continue;
+          }
+          edges += 1;
+          // Walking off an edge then straight back comes back to the start
+          let (landed, facing) = cube.wrap(position, &direction);
+          assert!(grid.contains_key(&landed));
+          assert_ne!(cube.face_of(&landed), cube.face_of(position));
+          assert_eq!(cube.wrap(&landed, &reverse(&facing)), (*position, reverse(&direction)));
+        }
+      }
+      // 7 edges are left open by the net, each with two sides of 3 tiles
+      assert_eq!(edges, 7 * 2 * 3);
+    }
+  }
+
+  #[test]
+  fn test_invalid_nets() {
+    assert_eq!(CubeNet::fold(&net(2, &[(0, 0), (1, 0)])).err(), Some(NetError::WrongTileCount(8)));
+    let straight = net(2, &[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)]);
+    assert_eq!(CubeNet::fold(&straight).err(), Some(NetError::NotACube));
+
+    let mut gap = net(2, &[(1, 0), (0, 1), (1, 1), (2, 1), (3, 1), (1, 2)]);
+    gap.remove(&Point { x: 3, y: 3 });
+    gap.insert(Point { x: 9, y: 9 }, '.');
+    assert_eq!(CubeNet::fold(&gap).err().unwrap().to_string(), "the face with its corner at 2,2 has gaps in it");
+  }
+
+  // The layout the puzzle input uses, which used to be hardcoded
+  #[test]
+  fn verify_3d_wrapping() {
+    let grid = net(50, &[(1, 0), (2, 0), (1, 1), (0, 2), (1, 2), (0, 3)]);
+    let cube = CubeNet::fold(&grid).unwrap();
+    let wrap = |x: i64, y: i64, d: Direction| cube.wrap(&Point { x, y }, &d);
+
+    // top I
+    assert_eq!(wrap(50, 0, Direction::Up), (Point { x: 0, y: 150 }, Direction::Right));
+    assert_eq!(wrap(99, 0, Direction::Up), (Point { x: 0, y: 199 }, Direction::Right));
+    // top H
+    assert_eq!(wrap(100, 0, Direction::Up), (Point { x: 0, y: 199 }, Direction::Up));
+    assert_eq!(wrap(149, 0, Direction::Up), (Point { x: 49, y: 199 }, Direction::Up));
+    // rightmost L
+    assert_eq!(wrap(149, 0, Direction::Right), (Point { x: 99, y: 149 }, Direction::Left));
+    assert_eq!(wrap(149, 49, Direction::Right), (Point { x: 99, y: 100 }, Direction::Left));
+    // upper B
+    assert_eq!(wrap(100, 49, Direction::Down), (Point { x: 99, y: 50 }, Direction::Left));
+    assert_eq!(wrap(149, 49, Direction::Down), (Point { x: 99, y: 99 }, Direction::Left));
+    // middle B
+    assert_eq!(wrap(99, 50, Direction::Right), (Point { x: 100, y: 49 }, Direction::Up));
+    assert_eq!(wrap(99, 99, Direction::Right), (Point { x: 149, y: 49 }, Direction::Up));
+    // middle L
+    assert_eq!(wrap(99, 100, Direction::Right), (Point { x: 149, y: 49 }, Direction::Left));
+    assert_eq!(wrap(99, 149, Direction::Right), (Point { x: 149, y: 0 }, Direction::Left));
+    // middle G
+    assert_eq!(wrap(50, 149, Direction::Down), (Point { x: 49, y: 150 }, Direction::Left));
+    assert_eq!(wrap(99, 149, Direction::Down), (Point { x: 49, y: 199 }, Direction::Left));
+    // bottom G
+    assert_eq!(wrap(49, 150, Direction::Right), (Point { x: 50, y: 149 }, Direction::Up));
+    assert_eq!(wrap(49, 199, Direction::Right), (Point { x: 99, y: 149 }, Direction::Up));
+    // bottom H
+    assert_eq!(wrap(0, 199, Direction::Down), (Point { x: 100, y: 0 }, Direction::Down));
+    assert_eq!(wrap(49, 199, Direction::Down), (Point { x: 149, y: 0 }, Direction::Down));
+    // bottom left I
+    assert_eq!(wrap(0, 150, Direction::Left), (Point { x: 50, y: 0 }, Direction::Down));
+    assert_eq!(wrap(0, 199, Direction::Left), (Point { x: 99, y: 0 }, Direction::Down));
+    // left bottom K
+    assert_eq!(wrap(0, 100, Direction::Left), (Point { x: 50, y: 49 }, Direction::Right));
+    assert_eq!(wrap(0, 149, Direction::Left), (Point { x: 50, y: 0 }, Direction::Right));
+    // left D
+    assert_eq!(wrap(0, 100, Direction::Up), (Point { x: 50, y: 50 }, Direction::Right));
+    assert_eq!(wrap(49, 100, Direction::Up), (Point { x: 50, y: 99 }, Direction::Right));
+    // middle D
+    assert_eq!(wrap(50, 50, Direction::Left), (Point { x: 0, y: 100 }, Direction::Down));
+    assert_eq!(wrap(50, 99, Direction::Left), (Point { x: 49, y: 100 }, Direction::Down));
+    // top K
+    assert_eq!(wrap(50, 0, Direction::Left), (Point { x: 0, y: 149 }, Direction::Right));
+    assert_eq!(wrap(50, 49, Direction::Left), (Point { x: 0, y: 100 }, Direction::Right));
+  }
+}
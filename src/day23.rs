@@ -1,8 +1,10 @@
 use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 
 use crate::day08::Point;
 use crate::day12::lines_to_grid_char_val;
 use crate::day23::Direction::{East, North, South, West};
+use crate::day23_bitboard::Bitboard;
 use crate::utils::read_chunks;
 
 pub fn part_one() -> i64 {
@@ -18,43 +20,65 @@ pub fn part_two() -> i64 {
 }
 
 fn solve_one(grid: &HashMap<Point, char>) -> i64 {
-  let mut next_grid = grid.clone();
-  println!("initial state");
-  // dbg_print_grid(&next_grid);
+  let mut board = Bitboard::from_grid(grid);
   let mut directions = VecDeque::from_iter(vec![North, South, West, East]);
 
-
   for _i in 0..10 {
-    let (_stable, updated_grid) = step(&next_grid, &directions);
-    // println!("iteration {}", i);
-    next_grid = updated_grid;
-    // dbg_print_grid(&next_grid);
+    board.round(&directions);
     directions.rotate_left(1);
   }
 
-  compute_empty_tiles(&next_grid)
+  board.empty_tiles()
 }
 
 fn solve_two(grid: &HashMap<Point, char>) -> i64 {
-  let mut next_grid = grid.clone();
-  println!("initial state");
-  // dbg_print_grid(&next_grid);
+  let mut board = Bitboard::from_grid(grid);
   let mut directions = VecDeque::from_iter(vec![North, South, West, East]);
   let mut stable = false;
   let mut round = 0;
   while !stable {
     round += 1;
-    let (updated_stable, updated_grid) = step(&next_grid, &directions);
-    // println!("iteration {}", i);
-    next_grid = updated_grid;
-    stable = updated_stable;
-    // dbg_print_grid(&next_grid);
+    stable = board.round(&directions);
     directions.rotate_left(1);
   }
 
   round
 }
 
+// Runs the original map-based rounds next to the bitboard ones, checking they
+// agree after every round. Returns how many rounds ran and the time each took
+pub fn compare_engines(grid: &HashMap<Point, char>, max_rounds: usize) -> (usize, Duration, Duration) {
+  let mut next_grid = grid.clone();
+  let mut board = Bitboard::from_grid(grid);
+  let mut directions = VecDeque::from_iter(vec![North, South, West, East]);
+  let (mut map_time, mut board_time) = (Duration::ZERO, Duration::ZERO);
+
+  for round in 1..=max_rounds {
+    let started = Instant::now();
+    let (stable, updated_grid) = step(&next_grid, &directions);
+    map_time += started.elapsed();
+    next_grid = updated_grid;
+
+    let started = Instant::now();
+    let board_stable = board.round(&directions);
+    board_time += started.elapsed();
+
+    let mut expected: Vec<Point> = next_grid.keys().copied().collect();
+    let mut found = board.elves();
+    expected.sort_by_key(|p| (p.y, p.x));
+    found.sort_by_key(|p| (p.y, p.x));
+    if expected != found || stable != board_stable || compute_empty_tiles(&next_grid) != board.empty_tiles() {
+      panic!("Bitboard differs from the map after round {}", round);
+    }
+    if stable {
+      return (round, map_time, board_time);
+    }
+    directions.rotate_left(1);
+  }
+
+  (max_rounds, map_time, board_time)
+}
+
 #[allow(dead_code)]
 pub fn dbg_print_grid(grid: &HashMap<Point, char>) {
   let min_x = grid.keys().map(|k| k.x).min().unwrap() - 1;
@@ -88,7 +112,7 @@ struct ProposedMove {
   stable: bool,
 }
 
-fn step(grid: &HashMap<Point, char>, directions: &VecDeque<Direction>) -> (bool, HashMap<Point, char>) {
+pub fn step(grid: &HashMap<Point, char>, directions: &VecDeque<Direction>) -> (bool, HashMap<Point, char>) {
   let mut proposed_moves: Vec<ProposedMove> = Vec::new();
 
   let elf_locations: Vec<Point> = grid.iter().filter(|(_k, v)| v == &&'#').map(|(k, _v)| k.clone()).collect();
@@ -147,7 +171,8 @@ fn all_neighbor_vectors() -> Vec<Point> {
   ]
 }
 
-enum Direction {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
   North,
   South,
   West,
@@ -155,7 +180,7 @@ enum Direction {
 }
 
 impl Direction {
-  fn consideration_vectors(&self) -> Vec<Point> {
+  pub fn consideration_vectors(&self) -> Vec<Point> {
     match self {
       North => vec![Point { x: 0, y: -1 }, Point { x: 1, y: -1 }, Point { x: -1, y: -1 }],
       South => vec![Point { x: 0, y: 1 }, Point { x: 1, y: 1 }, Point { x: -1, y: 1 }],
@@ -164,7 +189,17 @@ impl Direction {
     }
   }
 
-  fn move_vector(&self) -> Point {
+  // Where this direction's proposals go in `Bitboard::round`
+  pub fn index(&self) -> usize {
+    match self {
+      North => 0,
+      South => 1,
+      West => 2,
+      East => 3,
+    }
+  }
+
+  pub fn move_vector(&self) -> Point {
     match self {
       North => Point { x: 0, y: -1 },
       South => Point { x: 0, y: 1 },
@@ -0,0 +1,257 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::day08::Point;
+use crate::day23::Direction;
+
+const BITS: usize = 64;
+
+// The elves as one bit per tile, each row a run of `words` 64-bit words with
+// bit `x % 64` of word `x / 64` being column `x`. `origin` is where row 0,
+// column 0 sits on the map, and moves as the board grows to keep an empty
+// border around the elves
+pub struct Bitboard {
+  words: usize,
+  height: usize,
+  cells: Vec<u64>,
+  origin: Point,
+}
+
+impl Bitboard {
+  pub fn from_grid(grid: &HashMap<Point, char>) -> Bitboard {
+    let elves: Vec<Point> = grid.iter().filter(|(_k, v)| v == &&'#').map(|(k, _v)| *k).collect();
+    let min_x = elves.iter().map(|p| p.x).min().unwrap_or(0);
+    let max_x = elves.iter().map(|p| p.x).max().unwrap_or(0);
+    let min_y = elves.iter().map(|p| p.y).min().unwrap_or(0);
+    let max_y = elves.iter().map(|p| p.y).max().unwrap_or(0);
+
+    let words = (max_x - min_x) as usize / BITS + 1;
+    let height = (max_y - min_y) as usize + 1;
+    let mut board = Bitboard { words, height, cells: vec![0; words * height], origin: Point { x: min_x, y: min_y } };
+    for elf in elves {
+      let (x, y) = ((elf.x - min_x) as usize, (elf.y - min_y) as usize);
+      board.cells[y * words + x / BITS] |= 1 << (x % BITS);
+    }
+    board
+  }
+
+  pub fn elves(&self) -> Vec<Point> {
+    let mut elves = vec![];
+    for (index, word) in self.cells.iter().enumerate() {
+      let mut word = *word;
+      while word != 0 {
+        let bit = word.trailing_zeros() as usize;
+        word &= word - 1;
+        let (y, x) = (index / self.words, (index % self.words) * BITS + bit);
+        elves.push(Point { x: self.origin.x + x as i64, y: self.origin.y + y as i64 });
+      }
+    }
+    elves
+  }
+
+  // Empty tiles in the smallest box around the elves, worked out from the
+  // first and last set bit of each row. No elves means no box at all
+  pub fn empty_tiles(&self) -> i64 {
+    let mut count = 0;
+    let (mut min_x, mut max_x) = (usize::MAX, 0);
+    let (mut min_y, mut max_y) = (usize::MAX, 0);
+    for (y, row) in self.cells.chunks(self.words).enumerate() {
+      let (Some(first), Some(last)) = (row.iter().position(|w| *w != 0), row.iter().rposition(|w| *w != 0)) else {
+        continue;
+      };
+      min_x = min_x.min(first * BITS + row[first].trailing_zeros() as usize);
+      max_x = max_x.max(last * BITS + BITS - 1 - row[last].leading_zeros() as usize);
+      (min_y, max_y) = (min_y.min(y), y);
+      count += row.iter().map(|w| w.count_ones() as i64).sum::<i64>();
+    }
+    if count == 0 {
+      return 0;
+    }
+
+    (max_x - min_x + 1) as i64 * (max_y - min_y + 1) as i64 - count
+  }
+
+  // One round of proposing and moving, the same as `day23::step`. Returns
+  // whether every elf was already clear of the others, so nobody tried to move
+  pub fn round(&mut self, directions: &VecDeque<Direction>) -> bool {
+    self.grow();
+    let (words, height) = (self.words, self.height);
+
+    // Who proposed going each way, indexed by `Direction::index`
+    let mut proposals = [vec![0; self.cells.len()], vec![0; self.cells.len()], vec![0; self.cells.len()], vec![0; self.cells.len()]];
+    let mut restless = false;
+    for r in 0..height {
+      for i in 0..words {
+        let c = self.cells[r * words + i];
+        if c == 0 {
+          continue;
+        }
+        let (n, nw, ne) = neighbours(&self.cells, words, r as isize - 1, i);
+        let (_, w, e) = neighbours(&self.cells, words, r as isize, i);
+        let (s, sw, se) = neighbours(&self.cells, words, r as isize + 1, i);
+
+        // Tiles with nothing in the way, indexed like `proposals`
+        let free = [!(n | nw | ne), !(s | sw | se), !(nw | w | sw), !(ne | e | se)];
+        let mut left = c & !(free[0] & free[1] & free[2] & free[3]);
+        restless |= left != 0;
+        for d in directions {
+          let proposed = left & free[d.index()];
+          proposals[d.index()][r * words + i] = proposed;
+          left &= !proposed;
+        }
+      }
+    }
+    if !restless {
+      return true;
+    }
+
+    // Two elves can only want the same tile when they're facing each other
+    // across it, so a move is blocked exactly when there's an elf two tiles
+    // ahead proposing the opposite way
+    let [north, south, west, east] = &proposals;
+    let mut next = vec![0; self.cells.len()];
+    for r in 0..height {
+      for i in 0..words {
+        let at = |layer: &Vec<u64>, dr: isize| word_at(layer, words, r as isize + dr, i);
+        let index = r * words + i;
+        let moving = north[index] | south[index] | west[index] | east[index];
+
+        let (from_south, from_north) = (at(north, 1), at(south, -1));
+        let (from_west, from_east) = (shifted(east, words, r, i, 1), shifted(west, words, r, i, -1));
+        let arriving = ((from_south | from_north) & !(from_south & from_north))
+          | ((from_west | from_east) & !(from_west & from_east));
+        let blocked = (north[index] & at(south, -2))
+          | (south[index] & at(north, 2))
+          | (west[index] & shifted(east, words, r, i, 2))
+          | (east[index] & shifted(west, words, r, i, -2));
+
+        next[index] = (self.cells[index] & !moving) | blocked | arriving;
+      }
+    }
+    self.cells = next;
+    false
+  }
+
+  // Makes sure there's an empty row or column on every side for elves to move
+  // into
+  fn grow(&mut self) {
+    let words = self.words;
+    if self.cells[..words].iter().any(|w| *w != 0) {
+      self.cells.splice(0..0, vec![0; words]);
+      self.height += 1;
+      self.origin.y -= 1;
+    }
+    if self.cells[self.cells.len() - words..].iter().any(|w| *w != 0) {
+      self.cells.extend(vec![0; words]);
+      self.height += 1;
+    }
+
+    let left = self.cells.chunks(words).any(|row| row[0] & 1 != 0);
+    let right = self.cells.chunks(words).any(|row| row[words - 1] >> (BITS - 1) != 0);
+    if left || right {
+      let new_words = words + left as usize + right as usize;
+      let mut cells = Vec::with_capacity(new_words * self.height);
+      for row in self.cells.chunks(words) {
+        if left {
+          cells.push(0);
+        }
+        cells.extend_from_slice(row);
+        if right {
+          cells.push(0);
+        }
+      }
+      self.cells = cells;
+      self.words = new_words;
+      if left {
+        self.origin.x -= BITS as i64;
+      }
+    }
+  }
+}
+
+fn word_at(cells: &[u64], words: usize, r: isize, i: usize) -> u64 {
+  if r < 0 || r as usize * words >= cells.len() {
+    0
+  } else {
+    cells[r as usize * words + i]
+  }
+}
+
+// Word `i` of row `r` with each bit replaced by the one `by` columns to its
+// west, or to its east for negative `by`
+fn shifted(cells: &[u64], words: usize, r: usize, i: usize, by: isize) -> u64 {
+  let word = |i: usize| word_at(cells, words, r as isize, i);
+  let by_bits = by.unsigned_abs() as u32;
+  if by > 0 {
+    let carry = if i > 0 { word(i - 1) >> (BITS as u32 - by_bits) } else { 0 };
+    (word(i) << by_bits) | carry
+  } else {
+    let carry = if i + 1 < words { word(i + 1) << (BITS as u32 - by_bits) } else { 0 };
+    (word(i) >> by_bits) | carry
+  }
+}
+
+// Word `i` of row `r`, and the same word as seen from one column east and one
+// column west, so each bit says whether that tile's west or east neighbour is
+// an elf
+fn neighbours(cells: &[u64], words: usize, r: isize, i: usize) -> (u64, u64, u64) {
+  if r < 0 || r as usize * words >= cells.len() {
+    return (0, 0, 0);
+  }
+  let r = r as usize;
+  (cells[r * words + i], shifted(cells, words, r, i, 1), shifted(cells, words, r, i, -1))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::{HashMap, VecDeque};
+
+  use crate::day08::Point;
+  use crate::day12::lines_to_grid_char_val;
+  use crate::day23::{step, Direction::{East, North, South, West}};
+  use crate::day23_bitboard::Bitboard;
+
+  fn sorted(mut elves: Vec<Point>) -> Vec<Point> {
+    elves.sort_by_key(|p| (p.y, p.x));
+    elves
+  }
+
+  #[test]
+  fn test_matches_hashmap_rounds() {
+    // Wide enough to span two words and grow on every side
+    let mut lines = vec![];
+    let mut seed: u64 = 23;
+    for _ in 0..20 {
+      let mut line = String::new();
+      for _ in 0..70 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        line.push(if (seed >> 33).is_multiple_of(3) { '#' } else { '.' });
+      }
+      lines.push(line);
+    }
+    let mut grid: HashMap<Point, char> = lines_to_grid_char_val(&lines);
+    let mut board = Bitboard::from_grid(&grid);
+    let mut directions = VecDeque::from_iter(vec![North, South, West, East]);
+
+    for _ in 0..60 {
+      let (stable, next) = step(&grid, &directions);
+      assert_eq!(board.round(&directions), stable);
+      grid = next;
+      let expected = sorted(grid.keys().copied().collect());
+      assert_eq!(sorted(board.elves()), expected);
+      directions.rotate_left(1);
+    }
+  }
+
+  #[test]
+  fn test_empty_tiles() {
+    let lines = vec!["#...".to_string(), "....".to_string(), "..#.".to_string()];
+    let board = Bitboard::from_grid(&lines_to_grid_char_val(&lines));
+    assert_eq!(board.empty_tiles(), 7);
+    assert_eq!(sorted(board.elves()), vec![Point { x: 0, y: 0 }, Point { x: 2, y: 2 }]);
+
+    let mut empty = Bitboard::from_grid(&HashMap::new());
+    assert_eq!(empty.empty_tiles(), 0);
+    assert!(empty.round(&VecDeque::from_iter(vec![North, South, West, East])));
+    assert!(empty.elves().is_empty());
+  }
+}
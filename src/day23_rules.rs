@@ -0,0 +1,344 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::day08::Point;
+use crate::day23::Direction::{East, North, South, West};
+
+// A tile as x, y and z. Flat maps leave z at 0, and hexagonal ones use x and y
+// as axial coordinates
+pub type Cell = [i64; 3];
+
+fn add(a: Cell, b: Cell) -> Cell {
+  [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+// One way an elf can go, and the cells around it that have to be empty first
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proposal {
+  pub name: String,
+  pub step: Cell,
+  pub clear: Vec<Cell>,
+}
+
+// How often the first proposal moves to the back of the list
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+  Every(usize),
+  Never,
+}
+
+impl Rotation {
+  pub fn parse(text: &str) -> Option<Rotation> {
+    match text {
+      "never" => Some(Rotation::Never),
+      _ => text.strip_prefix("every:")?.parse().ok().filter(|n| *n > 0).map(Rotation::Every),
+    }
+  }
+}
+
+// A proposal name that isn't one of the rules' proposals
+#[derive(Debug, PartialEq)]
+pub struct UnknownProposal(pub String);
+
+impl fmt::Display for UnknownProposal {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "no proposal called {}", self.0)
+  }
+}
+
+// Everything that decides how the elves spread out. An elf only proposes a
+// move when at least `min_neighbours` of its `neighbourhood` are taken
+#[derive(Clone, Debug)]
+pub struct Rules {
+  pub neighbourhood: Vec<Cell>,
+  pub proposals: Vec<Proposal>,
+  pub rotation: Rotation,
+  pub min_neighbours: usize,
+}
+
+impl Rules {
+  // The puzzle's rules
+  pub fn standard() -> Rules {
+    let cell = |p: Point| [p.x, p.y, 0];
+    let proposals = [("N", North), ("S", South), ("W", West), ("E", East)]
+      .iter()
+      .map(|(name, d)| Proposal {
+        name: name.to_string(),
+        step: cell(d.move_vector()),
+        clear: d.consideration_vectors().into_iter().map(cell).collect(),
+      })
+      .collect();
+    let neighbourhood = (-1..=1)
+      .flat_map(|y| (-1..=1).map(move |x| [x, y, 0]))
+      .filter(|c| c != &[0, 0, 0])
+      .collect();
+    Rules { neighbourhood, proposals, rotation: Rotation::Every(1), min_neighbours: 1 }
+  }
+
+  // Six neighbours in axial coordinates. Each way needs that neighbour and the
+  // two either side of it to be empty
+  pub fn hexagonal() -> Rules {
+    let around = [("E", [1, 0, 0]), ("NE", [1, -1, 0]), ("NW", [0, -1, 0]), ("W", [-1, 0, 0]), ("SW", [-1, 1, 0]), ("SE", [0, 1, 0])];
+    let proposals = (0..around.len())
+      .map(|i| Proposal {
+        name: around[i].0.to_string(),
+        step: around[i].1,
+        clear: vec![around[(i + around.len() - 1) % around.len()].1, around[i].1, around[(i + 1) % around.len()].1],
+      })
+      .collect();
+    Rules {
+      neighbourhood: around.iter().map(|(_, c)| *c).collect(),
+      proposals: reorder(proposals, &["NW", "SE", "W", "E", "NE", "SW"]).unwrap(),
+      rotation: Rotation::Every(1),
+      min_neighbours: 1,
+    }
+  }
+
+  // All 26 cells of the surrounding cube, moving along an axis when the 9
+  // cells on that side are empty. Up and down are z
+  pub fn cubic() -> Rules {
+    let neighbourhood: Vec<Cell> = (-1..=1)
+      .flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| [x, y, z])))
+      .filter(|c| c != &[0, 0, 0])
+      .collect();
+    let sides = [("N", 1, -1), ("S", 1, 1), ("W", 0, -1), ("E", 0, 1), ("U", 2, -1), ("D", 2, 1)];
+    let proposals = sides
+      .iter()
+      .map(|(name, axis, sign)| {
+        let mut step = [0; 3];
+        step[*axis] = *sign;
+        Proposal {
+          name: name.to_string(),
+          step,
+          clear: neighbourhood.iter().filter(|c| c[*axis] == *sign).copied().collect(),
+        }
+      })
+      .collect();
+    Rules { neighbourhood, proposals, rotation: Rotation::Every(1), min_neighbours: 1 }
+  }
+
+  pub fn by_name(name: &str) -> Option<Rules> {
+    match name {
+      "square" => Some(Rules::standard()),
+      "hex" => Some(Rules::hexagonal()),
+      "cube" => Some(Rules::cubic()),
+      _ => None,
+    }
+  }
+
+  // The same proposals tried in a different order, which needn't include them
+  // all
+  pub fn with_order(mut self, names: &[&str]) -> Result<Rules, UnknownProposal> {
+    self.proposals = reorder(self.proposals, names)?;
+    Ok(self)
+  }
+
+  // Only the cell being moved into has to be empty
+  pub fn front_only(mut self) -> Rules {
+    for proposal in self.proposals.iter_mut() {
+      proposal.clear = vec![proposal.step];
+    }
+    self
+  }
+
+  // The proposals in the order they're tried in a round, counting from 0
+  fn order(&self, round: usize) -> impl Iterator<Item = &Proposal> {
+    let shift = match self.rotation {
+      Rotation::Every(rounds) => round / rounds,
+      Rotation::Never => 0,
+    };
+    let len = self.proposals.len().max(1);
+    (0..self.proposals.len()).map(move |i| &self.proposals[(i + shift) % len])
+  }
+}
+
+fn reorder(proposals: Vec<Proposal>, names: &[&str]) -> Result<Vec<Proposal>, UnknownProposal> {
+  names
+    .iter()
+    .map(|name| {
+      proposals.iter().find(|p| p.name == *name).cloned().ok_or_else(|| UnknownProposal(name.to_string()))
+    })
+    .collect()
+}
+
+// What happened in one round, and the smallest box around the elves after it.
+// `empty` is how many cells in that box have no elf
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundStats {
+  pub round: usize,
+  pub proposing: usize,
+  pub moved: usize,
+  pub min: Cell,
+  pub max: Cell,
+  pub empty: i64,
+}
+
+pub fn elves_from_grid(grid: &HashMap<Point, char>) -> HashSet<Cell> {
+  grid.iter().filter(|(_k, v)| v == &&'#').map(|(k, _v)| [k.x, k.y, 0]).collect()
+}
+
+// With no elves the box is the empty one at the origin
+fn bounds(elves: &HashSet<Cell>) -> (Cell, Cell, i64) {
+  if elves.is_empty() {
+    return ([0; 3], [0; 3], 0);
+  }
+  let mut min = [i64::MAX; 3];
+  let mut max = [i64::MIN; 3];
+  for elf in elves {
+    for axis in 0..3 {
+      min[axis] = min[axis].min(elf[axis]);
+      max[axis] = max[axis].max(elf[axis]);
+    }
+  }
+  let volume = (0..3).map(|axis| max[axis] - min[axis] + 1).product::<i64>();
+  (min, max, volume - elves.len() as i64)
+}
+
+// Round `index` (counting from 0) under `rules`
+pub fn round(elves: &HashSet<Cell>, rules: &Rules, index: usize) -> (HashSet<Cell>, RoundStats) {
+  let mut destinations: HashMap<Cell, Vec<Cell>> = HashMap::new();
+  let mut proposing = 0;
+  let mut staying = vec![];
+  for elf in elves {
+    let taken = |delta: &Cell| elves.contains(&add(*elf, *delta));
+    if rules.neighbourhood.iter().filter(|d| taken(d)).count() < rules.min_neighbours {
+      staying.push(*elf);
+      continue;
+    }
+    proposing += 1;
+    match rules.order(index).find(|p| !p.clear.iter().any(taken)) {
+      Some(p) => destinations.entry(add(*elf, p.step)).or_default().push(*elf),
+      None => staying.push(*elf),
+    }
+  }
+
+  let mut moved = 0;
+  let mut next: HashSet<Cell> = staying.into_iter().collect();
+  for (destination, from) in destinations {
+    if from.len() == 1 {
+      moved += 1;
+      next.insert(destination);
+    } else {
+      next.extend(from);
+    }
+  }
+
+  let (min, max, empty) = bounds(&next);
+  (next, RoundStats { round: index + 1, proposing, moved, min, max, empty })
+}
+
+// Stats for each round until nobody wants to move, or `max_rounds` have run
+pub fn simulate(elves: &HashSet<Cell>, rules: &Rules, max_rounds: usize) -> Vec<RoundStats> {
+  let mut elves = elves.clone();
+  let mut stats = vec![];
+  for i in 0..max_rounds {
+    let (next, round_stats) = round(&elves, rules, i);
+    elves = next;
+    let settled = round_stats.proposing == 0;
+    stats.push(round_stats);
+    if settled {
+      break;
+    }
+  }
+  stats
+}
+
+pub fn stats_to_table(stats: &[RoundStats]) -> String {
+  let mut text = format!("{:>6} {:>9} {:>6} {:>22} {:>22} {:>7}\n", "round", "proposing", "moved", "min", "max", "empty");
+  for s in stats {
+    let cell = |c: Cell| format!("{},{},{}", c[0], c[1], c[2]);
+    text.push_str(&format!("{:>6} {:>9} {:>6} {:>22} {:>22} {:>7}\n", s.round, s.proposing, s.moved, cell(s.min), cell(s.max), s.empty));
+  }
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use crate::day12::lines_to_grid_char_val;
+  use crate::day23_rules::{elves_from_grid, Rotation, Rules, simulate, stats_to_table, UnknownProposal};
+
+  fn sample() -> Vec<String> {
+    vec![
+      "....#..".to_string(),
+      "..###.#".to_string(),
+      "#...#.#".to_string(),
+      ".#...##".to_string(),
+      "#.###..".to_string(),
+      "##.#.##".to_string(),
+      ".#..#..".to_string(),
+    ]
+  }
+
+  #[test]
+  fn test_standard_rules_match_puzzle() {
+    let elves = elves_from_grid(&lines_to_grid_char_val(&sample()));
+    let stats = simulate(&elves, &Rules::standard(), 1000);
+    assert_eq!(stats[9].empty, 110);
+    assert_eq!(stats.len(), 20);
+    assert_eq!(stats.last().unwrap().moved, 0);
+    assert!(stats[0].moved > 0 && stats[0].proposing >= stats[0].moved);
+
+    let table = stats_to_table(&stats[..1]);
+    assert_eq!(table.lines().count(), 2);
+  }
+
+  #[test]
+  fn test_order_and_rotation() {
+    // Two elves side by side. With only north allowed and no rotation they
+    // both go north every round until they run out of rounds
+    let elves: HashSet<[i64; 3]> = [[0, 0, 0], [1, 0, 0]].into_iter().collect();
+    let mut rules = Rules::standard().with_order(&["N"]).unwrap();
+    rules.rotation = Rotation::Never;
+    let stats = simulate(&elves, &rules, 5);
+    assert_eq!(stats.len(), 5);
+    assert_eq!(stats[4].min, [0, -5, 0]);
+
+    assert_eq!(Rules::standard().with_order(&["N", "Q"]).unwrap_err(), UnknownProposal("Q".to_string()));
+    assert_eq!(Rotation::parse("every:3"), Some(Rotation::Every(3)));
+    assert_eq!(Rotation::parse("every:0"), None);
+    assert_eq!(Rotation::parse("never"), Some(Rotation::Never));
+  }
+
+  #[test]
+  fn test_neighbour_requirements() {
+    // A lone pair never moves when elves need two neighbours to bother
+    let elves: HashSet<[i64; 3]> = [[0, 0, 0], [1, 0, 0]].into_iter().collect();
+    let mut rules = Rules::standard();
+    rules.min_neighbours = 2;
+    let stats = simulate(&elves, &rules, 5);
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].proposing, 0);
+
+    // With only the cell ahead needing to be empty, both can go north
+    let stats = simulate(&elves, &Rules::standard().front_only(), 1);
+    assert_eq!(stats[0].moved, 2);
+  }
+
+  #[test]
+  fn test_other_neighbourhoods() {
+    let hex = Rules::hexagonal();
+    assert_eq!(hex.neighbourhood.len(), 6);
+    assert!(hex.proposals.iter().all(|p| p.clear.len() == 3 && p.clear.contains(&p.step)));
+    let cube = Rules::cubic();
+    assert_eq!(cube.neighbourhood.len(), 26);
+    assert!(cube.proposals.iter().all(|p| p.clear.len() == 9));
+
+    // A pair on a hex grid splits up straight away, and in 3D after going
+    // north and south together
+    let elves: HashSet<[i64; 3]> = [[0, 0, 0], [1, 0, 0]].into_iter().collect();
+    let stats = simulate(&elves, &hex, 10);
+    assert_eq!(stats.iter().map(|s| s.moved).collect::<Vec<_>>(), vec![2, 0]);
+    let stats = simulate(&elves, &cube, 10);
+    assert_eq!(stats.iter().map(|s| s.moved).collect::<Vec<_>>(), vec![2, 2, 2, 0]);
+  }
+
+  #[test]
+  fn test_no_elves() {
+    let stats = simulate(&HashSet::new(), &Rules::standard(), 5);
+    assert_eq!(stats.len(), 1);
+    assert_eq!((stats[0].proposing, stats[0].moved, stats[0].empty), (0, 0, 0));
+    assert_eq!((stats[0].min, stats[0].max), ([0; 3], [0; 3]));
+  }
+}
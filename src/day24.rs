@@ -1,5 +1,5 @@
-use std::cmp::min;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashMap;
+use std::fmt;
 
 use crate::day08::Point;
 use crate::day12::lines_to_grid_char_val;
@@ -20,116 +20,19 @@ pub fn part_two() -> u64 {
 
 fn solve_one(grid: &HashMap<Point, char>) -> u64 {
   let (start, end) = get_start_end(grid);
-  let walls = get_walls(grid);
-  let blizzards = get_blizzards(grid);
-  let all_indexed_blizzard_state_locations = index_all_blizzard_state_locations(&walls, &blizzards);
+  let valley = Valley::parse(grid);
+  let route = valley.route(&[start, end], 0).unwrap_or_else(|e| panic!("{}", e));
 
-  find_shortest_path(&start, &end, &walls, &all_indexed_blizzard_state_locations, 0) as u64
+  route.total() as u64
 }
 
 
 fn solve_two(grid: &HashMap<Point, char>) -> u64 {
   let (start, end) = get_start_end(grid);
-  let walls = get_walls(grid);
-  let blizzards = get_blizzards(grid);
-  let all_indexed_blizzard_state_locations = index_all_blizzard_state_locations(&walls, &blizzards);
+  let valley = Valley::parse(grid);
+  let route = valley.route(&[start, end, start, end], 0).unwrap_or_else(|e| panic!("{}", e));
 
-  let to_the_end = find_shortest_path(&start, &end, &walls, &all_indexed_blizzard_state_locations, 0);
-  let back_to_beginning = find_shortest_path(&end, &start, &walls, &all_indexed_blizzard_state_locations, to_the_end);
-  let and_the_end_again = find_shortest_path(&start, &end, &walls, &all_indexed_blizzard_state_locations, back_to_beginning);
-
-  and_the_end_again as u64
-}
-
-fn find_shortest_path(
-  start: &Point,
-  end: &Point,
-  walls: &HashSet<Point>,
-  all_indexed_blizzard_state_locations: &HashMap<usize, HashSet<Point>>,
-  starting_step: usize
-) -> usize {
-
-  let default_path_upper_bound = 1_000_000_000_000;
-  let mut shortest_path: usize = default_path_upper_bound;
-
-  let min_y = walls.iter().map(|k| k.y).min().unwrap();
-  let max_y = walls.iter().map(|k| k.y).max().unwrap();
-
-  let mut to_visit = VecDeque::new();
-  to_visit.push_back(State { steps: starting_step, position: start.clone() });
-  let mut visited = HashSet::new();
-
-  while !to_visit.is_empty() {
-    let curr = to_visit.pop_front().unwrap();
-
-    // // Debug breakpoints
-    // let test_point = Point { x: 4, y: 3 };
-    // let test_step = 14;
-    // if curr.position == test_point && curr.steps == test_step {
-    //   let blizzards = all_indexed_blizzard_state_locations
-    //     .get(&((curr.steps) % all_indexed_blizzard_state_locations.len())).unwrap();
-    //
-    //   println!("Minute: {}", test_step);
-    //   dbg_print_grid(
-    //     &walls,
-    //     blizzards,
-    //     &curr
-    //   );
-    //
-    //   let maybe_blizzards = all_indexed_blizzard_state_locations
-    //     .get(&((curr.steps + 1) % all_indexed_blizzard_state_locations.len())).unwrap();
-    //   println!("maybe minute 15");
-    //   dbg_print_grid(
-    //     &walls,
-    //     maybe_blizzards,
-    //     &State { steps: 15, position: Point{x: 1, y: 0}, }
-    //   )
-    // }
-
-    if curr.position == *end {
-      shortest_path = min(shortest_path, curr.steps);
-    }
-    if curr.steps <= shortest_path as usize {
-      let maybe_next_positions = next_step_vectors()
-        .iter()
-        .map(|v| curr.position + *v)
-        .collect::<Vec<Point>>();
-      let next_blizzard_positions = all_indexed_blizzard_state_locations
-        .get(&((curr.steps + 1) % all_indexed_blizzard_state_locations.len())).unwrap();
-
-      maybe_next_positions
-        .iter()
-        .for_each(|p| {
-          let is_valid = !next_blizzard_positions.contains(p) && !walls.contains(p) && p.y >= min_y && p.y <= max_y;
-
-          if is_valid {
-            let next_state = State { steps: curr.steps + 1, position: p.clone() };
-            let normalized_next_state = normalize_state(
-              all_indexed_blizzard_state_locations.len(),
-              next_state,
-            );
-            if !visited.contains(&normalized_next_state) && !to_visit.contains(&next_state) {
-              to_visit.push_back(next_state)
-            }
-            visited.insert(normalize_state(all_indexed_blizzard_state_locations.len(), curr));
-          }
-        })
-    }
-  }
-
-  if shortest_path == default_path_upper_bound {
-    panic!("never found a path");
-  }
-
-  shortest_path
-}
-
-
-fn normalize_state(blizzard_states: usize, next_state: State) -> State {
-  State {
-    steps: next_state.steps % blizzard_states,
-    position: next_state.position.clone(),
-  }
+  route.total() as u64
 }
 
 fn next_step_vectors() -> Vec<Point> {
@@ -142,105 +45,278 @@ fn next_step_vectors() -> Vec<Point> {
   ]
 }
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash)]
-struct State {
-  steps: usize,
-  position: Point,
+fn gcd(a: usize, b: usize) -> usize {
+  if b == 0 { a } else { gcd(b, a % b) }
 }
 
-fn index_all_blizzard_state_locations(walls: &HashSet<Point>, blizzards: &Vec<Blizzard>) -> HashMap<usize, HashSet<Point>> {
-  let mut blizzard_states = HashMap::new();
-
-  // Insert t=0
-  blizzard_states.insert(0, blizzards.iter().map(|b| b.position.clone()).collect());
-
-  // Now compute and insert them until we fully wrap around
-  let min_x = walls.iter().map(|k| k.x).min().unwrap();
-  let max_x = walls.iter().map(|k| k.x).max().unwrap();
-  let min_y = walls.iter().map(|k| k.y).min().unwrap();
-  let max_y = walls.iter().map(|k| k.y).max().unwrap();
-  let width = max_x - min_x - 1;
-  let height = max_y - min_y - 1;
-
-  let mut next_blizzards = blizzards.clone();
-  for i in 0..(height * width) {
-    blizzard_states.insert(i as usize, next_blizzards.iter().map(|b| b.position.clone()).collect());
-    let tmp_blizzards = step_blizzards(i, walls, &next_blizzards);
-    next_blizzards = tmp_blizzards;
-  }
+type Bits = Vec<u64>;
+
+fn bit(bits: &Bits, i: usize) -> bool {
+  bits[i / 64] >> (i % 64) & 1 == 1
+}
 
-  blizzard_states
+fn set_bit(bits: &mut Bits, i: usize) {
+  bits[i / 64] |= 1 << (i % 64);
 }
 
-fn step_blizzards(_index: i64, walls: &HashSet<Point>, curr_blizzards: &Vec<Blizzard>) -> Vec<Blizzard> {
-  let mut next_blizzards = Vec::new();
-
-  let min_x = walls.iter().map(|k| k.x).min().unwrap();
-  let max_x = walls.iter().map(|k| k.x).max().unwrap();
-  let min_y = walls.iter().map(|k| k.y).min().unwrap();
-  let max_y = walls.iter().map(|k| k.y).max().unwrap();
-
-  for b in curr_blizzards {
-    let maybe_next_position = get_next_position(b);
-    if walls.contains(&maybe_next_position) {
-      let wrap_position = match b.direction {
-        '<' => Point { x: max_x - 1, y: b.position.y },
-        '>' => Point { x: min_x + 1, y: b.position.y },
-        '^' => Point { x: b.position.x, y: max_y - 1 },
-        'v' => Point { x: b.position.x, y: min_y + 1 },
-        _ => panic!("unexpected blizzard direction"),
-      };
-      next_blizzards.push(Blizzard { position: wrap_position, direction: b.direction.clone() })
-    } else {
-      next_blizzards.push(Blizzard { position: maybe_next_position, direction: b.direction.clone() })
+// `bits` moved `by` places towards the higher bits, or the lower ones for a
+// negative `by`, keeping the same number of words
+fn shift(bits: &Bits, by: isize) -> Bits {
+  let (words, offset) = ((by.unsigned_abs() / 64) as isize, by.unsigned_abs() % 64);
+  let get = |j: isize| if j >= 0 && (j as usize) < bits.len() { bits[j as usize] } else { 0 };
+  (0..bits.len() as isize)
+    .map(|i| {
+      if by >= 0 {
+        let carry = if offset > 0 { get(i - words - 1) >> (64 - offset) } else { 0 };
+        (get(i - words) << offset) | carry
+      } else {
+        let carry = if offset > 0 { get(i + words + 1) << (64 - offset) } else { 0 };
+        (get(i + words) >> offset) | carry
+      }
+    })
+    .collect()
+}
+
+// The first `width` bits of `bits` turned round so that bit `x` is what was at
+// `(x + by) % width`
+fn rotate(bits: &Bits, width: usize, by: usize) -> Bits {
+  let by = (by % width) as isize;
+  let mut rotated: Bits = shift(bits, -by).iter().zip(shift(bits, width as isize - by)).map(|(a, b)| a | b).collect();
+  for (i, word) in rotated.iter_mut().enumerate() {
+    let bits_below = width.saturating_sub(i * 64);
+    if bits_below < 64 {
+      *word &= (1u64 << bits_below) - 1;
     }
   }
+  rotated
+}
 
-  // println!("Minute {}", _index);
-  // dbg_print_grid_with_blizzard_dirs(walls, &next_blizzards);
-  // println!();
+// The map with its blizzards as bitsets over where they started, one per row
+// of the inside of the valley. Blizzards only ever go round their own row or
+// column, so `<` and `>` at some minute are their row's bits turned round by
+// the minute, and `^` and `v` are the bits of the row they started in that
+// many rows away. `open` has a bit for every tile that isn't wall, row by row
+// over the whole map
+pub struct Valley {
+  width: usize,
+  height: usize,
+  open: Vec<Bits>,
+  left: Vec<Bits>,
+  right: Vec<Bits>,
+  up: Vec<Bits>,
+  down: Vec<Bits>,
+}
 
-  next_blizzards
+// What it took to visit each waypoint in turn. `legs` has how many minutes each
+// leg took, and `path` where the expedition is at each minute from the start,
+// waits included
+#[derive(Debug, PartialEq)]
+pub struct Route {
+  pub start_minute: usize,
+  pub legs: Vec<usize>,
+  pub path: Vec<Point>,
 }
 
-fn get_next_position(blizzard: &Blizzard) -> Point {
-  match blizzard.direction {
-    '<' => blizzard.position + Point { x: -1, y: 0 },
-    '>' => blizzard.position + Point { x: 1, y: 0 },
-    '^' => blizzard.position + Point { x: 0, y: -1 },
-    'v' => blizzard.position + Point { x: 0, y: 1 },
-    _ => panic!("unexpected blizzard direction"),
+impl Route {
+  pub fn total(&self) -> usize {
+    self.legs.iter().sum()
   }
 }
 
-
-fn get_walls(grid: &HashMap<Point, char>) -> HashSet<Point> {
-  grid
-    .iter()
-    .filter(|(_k, v)| v == &&'#')
-    .map(|(k, _v)| k.clone())
-    .collect()
+#[derive(Debug, PartialEq)]
+pub enum RouteError {
+  NotOpen(Point),
+  NoPath { leg: usize, from: Point, to: Point },
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-struct Blizzard {
-  position: Point,
-  direction: char,
+impl fmt::Display for RouteError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RouteError::NotOpen(p) => write!(f, "waypoint {},{} is not an open tile", p.x, p.y),
+      RouteError::NoPath { leg, from, to } => write!(f, "leg {} can never get from {},{} to {},{}", leg, from.x, from.y, to.x, to.y),
+    }
+  }
 }
 
-fn get_blizzards(grid: &HashMap<Point, char>) -> Vec<Blizzard> {
-  let blizzards = vec!['<', '>', '^', 'v'];
-  grid
-    .iter()
-    .filter(|(_k, v)| blizzards.contains(v))
-    .map(|(k, v)| Blizzard {
-      position: k.clone(),
-      direction: v.clone(),
-    })
-    .collect()
+impl Valley {
+  pub fn parse(grid: &HashMap<Point, char>) -> Valley {
+    let columns = grid.keys().map(|k| k.x).max().unwrap() as usize + 1;
+    let rows = grid.keys().map(|k| k.y).max().unwrap() as usize + 1;
+    let (width, height) = (columns - 2, rows - 2);
+    let words = |n: usize| vec![0; n / 64 + 1];
+
+    let mut valley = Valley {
+      width,
+      height,
+      open: vec![words(columns); rows],
+      left: vec![words(width); height],
+      right: vec![words(width); height],
+      up: vec![words(width); height],
+      down: vec![words(width); height],
+    };
+    for (p, c) in grid {
+      let (x, y) = (p.x as usize, p.y as usize);
+      if *c != '#' {
+        set_bit(&mut valley.open[y], x);
+      }
+      match c {
+        '<' => set_bit(&mut valley.left[y - 1], x - 1),
+        '>' => set_bit(&mut valley.right[y - 1], x - 1),
+        '^' => set_bit(&mut valley.up[y - 1], x - 1),
+        'v' => set_bit(&mut valley.down[y - 1], x - 1),
+        _ => {}
+      }
+    }
+    valley
+  }
+
+  // After this many minutes the blizzards are back where they started
+  pub fn period(&self) -> usize {
+    self.width * self.height / gcd(self.width, self.height)
+  }
+
+  fn blizzards_at(&self, position: &Point, minute: usize) -> Vec<char> {
+    let (x, y) = (position.x - 1, position.y - 1);
+    if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+      return vec![];
+    }
+    let (x, y, w, h) = (x as usize, y as usize, self.width, self.height);
+    let (across, along) = (minute % w, minute % h);
+    [
+      ('<', bit(&self.left[y], (x + across) % w)),
+      ('>', bit(&self.right[y], (x + w - across) % w)),
+      ('^', bit(&self.up[(y + along) % h], x)),
+      ('v', bit(&self.down[(y + h - along) % h], x)),
+    ]
+      .iter()
+      .filter(|(_, here)| *here)
+      .map(|(c, _)| *c)
+      .collect()
+  }
+
+  fn is_open(&self, position: &Point) -> bool {
+    position.x >= 0 && position.y >= 0 && (position.y as usize) < self.open.len()
+      && (position.x as usize) < self.width + 2 && bit(&self.open[position.y as usize], position.x as usize)
+  }
+
+  // The tiles that are open and clear of blizzards at `minute`, row by row
+  fn clear(&self, minute: usize) -> Vec<Bits> {
+    let (w, h) = (self.width, self.height);
+    let mut clear = self.open.clone();
+    for y in 0..h {
+      let up = &self.up[(y + minute) % h];
+      let down = &self.down[(y + h - minute % h) % h];
+      let right = rotate(&self.right[y], w, w - minute % w);
+      let mut blocked: Bits = rotate(&self.left[y], w, minute)
+        .iter()
+        .zip(right)
+        .zip(up.iter().zip(down))
+        .map(|((l, r), (u, d))| l | r | u | d)
+        .collect();
+      // Inside the walls, so one column across on the whole map
+      blocked.resize(clear[y + 1].len(), 0);
+      clear[y + 1].iter_mut().zip(shift(&blocked, 1)).for_each(|(c, b)| *c &= !b);
+    }
+    clear
+  }
+
+  // Visits `waypoints` in order as fast as possible, setting off at
+  // `start_minute`
+  pub fn route(&self, waypoints: &[Point], start_minute: usize) -> Result<Route, RouteError> {
+    if let Some(p) = waypoints.iter().find(|p| !self.is_open(p)) {
+      return Err(RouteError::NotOpen(*p));
+    }
+    let mut route = Route { start_minute, legs: vec![], path: waypoints.iter().take(1).copied().collect() };
+    let mut clear = vec![None; self.period()];
+    let mut minute = start_minute;
+    for (leg, pair) in waypoints.windows(2).enumerate() {
+      let path = self.leg(pair[0], pair[1], minute, &mut clear)
+        .ok_or(RouteError::NoPath { leg: leg + 1, from: pair[0], to: pair[1] })?;
+      minute += path.len() - 1;
+      route.legs.push(path.len() - 1);
+      route.path.extend_from_slice(&path[1..]);
+    }
+    Ok(route)
+  }
+
+  // Where the expedition is each minute on the quickest way from `from` to
+  // `to`. Every tile reachable by each minute is kept as bitsets, which is
+  // enough to walk back from `to` once it's reached. Reaching the same tiles at
+  // the same point in the blizzards' cycle again means it never will be. The
+  // clear tiles repeat with the blizzards, so `clear` keeps them by minute
+  // into the cycle for every leg to share
+  fn leg(&self, from: Point, to: Point, start_minute: usize, clear: &mut [Option<Vec<Bits>>]) -> Option<Vec<Point>> {
+    let rows = self.open.len();
+    let period = self.period();
+    let mut reached = vec![vec![vec![0; self.open[0].len()]; rows]];
+    set_bit(&mut reached[0][from.y as usize], from.x as usize);
+
+    let mut minute = start_minute;
+    while !bit(&reached[minute - start_minute][to.y as usize], to.x as usize) {
+      let now = &reached[minute - start_minute];
+      if now.iter().all(|row| row.iter().all(|w| *w == 0)) {
+        return None;
+      }
+      if minute - start_minute >= period && now == &reached[minute - start_minute - period] {
+        return None;
+      }
+
+      let clear = clear[(minute + 1) % period].get_or_insert_with(|| self.clear(minute + 1));
+      let next = (0..rows)
+        .map(|y| {
+          let mut row: Bits = shift(&now[y], 1).iter().zip(shift(&now[y], -1)).map(|(a, b)| a | b).collect();
+          row.iter_mut().zip(&now[y]).for_each(|(w, o)| *w |= o);
+          for neighbour in [y.wrapping_sub(1), y + 1] {
+            if let Some(other) = now.get(neighbour) {
+              row.iter_mut().zip(other).for_each(|(w, o)| *w |= o);
+            }
+          }
+          row.iter_mut().zip(&clear[y]).for_each(|(w, c)| *w &= c);
+          row
+        })
+        .collect();
+      reached.push(next);
+      minute += 1;
+    }
+
+    let mut path = vec![to];
+    for t in (0..reached.len() - 1).rev() {
+      let here = *path.last().unwrap();
+      let previous = next_step_vectors()
+        .iter()
+        .map(|v| here + *v)
+        .find(|p| p.x >= 0 && p.y >= 0 && (p.y as usize) < rows && (p.x as usize) < self.width + 2
+          && bit(&reached[t][p.y as usize], p.x as usize))
+        .unwrap();
+      path.push(previous);
+    }
+    path.reverse();
+    Some(path)
+  }
+
+  // The map at `minute` the way the puzzle draws it, with an `E` for the
+  // expedition
+  pub fn render(&self, minute: usize, expedition: Option<Point>) -> String {
+    let mut text = String::new();
+    for y in 0..self.open.len() {
+      for x in 0..self.width + 2 {
+        let p = Point { x: x as i64, y: y as i64 };
+        let blizzards = self.blizzards_at(&p, minute);
+        text.push(match blizzards.len() {
+          _ if expedition == Some(p) => 'E',
+          _ if !self.is_open(&p) => '#',
+          0 => '.',
+          1 => blizzards[0],
+          n => char::from_digit(n as u32, 10).unwrap(),
+        });
+      }
+      text.push('\n');
+    }
+    text
+  }
 }
 
-fn get_start_end(grid: &HashMap<Point, char>) -> (Point, Point) {
+pub fn get_start_end(grid: &HashMap<Point, char>) -> (Point, Point) {
   let start = grid
     .iter()
     .filter(|(k, v)| k.y == 0 && v == &&'.')
@@ -258,69 +334,11 @@ fn get_start_end(grid: &HashMap<Point, char>) -> (Point, Point) {
   (start.clone(), end.clone())
 }
 
-#[allow(dead_code)]
-fn dbg_print_grid(walls: &HashSet<Point>, blizzards: &HashSet<Point>, x1: &State) {
-  let mut grid = HashMap::new();
-
-  walls.iter().for_each(|p| { grid.insert(p.clone(), '#'); });
-  blizzards.iter().for_each(|p| { grid.insert(p.clone(), 'B'); });
-  grid.insert(x1.position.clone(), 'E');
-
-  let min_x = grid.keys().map(|k| k.x).min().unwrap();
-  let max_x = grid.keys().map(|k| k.x).max().unwrap();
-  let min_y = grid.keys().map(|k| k.y).min().unwrap();
-  let max_y = grid.keys().map(|k| k.y).max().unwrap();
-
-  for y in min_y..=max_y {
-    for x in min_x..=max_x {
-      print!("{}", grid.get(&Point { x, y }).unwrap_or(&'.'));
-    }
-    println!();
-  }
-}
-
-#[allow(dead_code)]
-fn dbg_print_grid_with_blizzard_dirs(walls: &HashSet<Point>, blizzards: &Vec<Blizzard>) {
-  let mut grid = HashMap::new();
-
-  walls.iter().for_each(|p| { grid.insert(p.clone(), '#'); });
-  blizzards.iter()
-    .for_each(|b| {
-      let existing = grid.get(&b.position).unwrap_or(&'.').clone();
-      if existing != '.' {
-        match existing.to_string().parse::<usize>() {
-          Ok(num) => {
-            grid.insert(b.position.clone(), (num + 1).to_string().chars().nth(0).unwrap());
-          }
-          Err(_blizzard) => {
-            grid.insert(b.position.clone(), '2');
-          }
-        }
-
-
-      } else {
-        grid.insert(b.position.clone(), b.direction);
-      }
-    }
-    );
-
-  let min_x = grid.keys().map(|k| k.x).min().unwrap();
-  let max_x = grid.keys().map(|k| k.x).max().unwrap();
-  let min_y = grid.keys().map(|k| k.y).min().unwrap();
-  let max_y = grid.keys().map(|k| k.y).max().unwrap();
-
-  for y in min_y..=max_y {
-    for x in min_x..=max_x {
-      print!("{}", grid.get(&Point { x, y }).unwrap_or(&'.'));
-    }
-    println!();
-  }
-}
-
 #[cfg(test)]
 mod tests {
+  use crate::day08::Point;
   use crate::day12::lines_to_grid_char_val;
-  use crate::day24::{get_blizzards, get_walls, index_all_blizzard_state_locations, solve_one, solve_two};
+  use crate::day24::{bit, Bits, get_start_end, rotate, RouteError, set_bit, solve_one, solve_two, Valley};
 
   #[test]
   fn test_2_blizzard_environ() {
@@ -335,9 +353,40 @@ mod tests {
     ];
 
     let grid = lines_to_grid_char_val(&map_lines);
-    let walls = get_walls(&grid);
-    let blizzards = get_blizzards(&grid);
-    let _indexed_locations = index_all_blizzard_state_locations(&walls, &blizzards);
+    let valley = Valley::parse(&grid);
+    assert_eq!(valley.period(), 5);
+    assert_eq!(valley.render(0, None), map_lines.join("\n") + "\n");
+    // Both blizzards move onto the same tile after a minute
+    assert_eq!(valley.render(1, None).lines().nth(2), Some("#...2.#"));
+    assert_eq!(valley.render(5, None), valley.render(0, None));
+  }
+
+  #[test]
+  fn test_rotate_across_words() {
+    // Bit x of the result is what was at (x + by) % width
+    let mut bits = vec![0; 2];
+    set_bit(&mut bits, 0);
+    set_bit(&mut bits, 70);
+    set_bit(&mut bits, 99);
+    let ones = |bits: &Bits| (0..128).filter(|i| bit(bits, *i)).collect::<Vec<usize>>();
+    assert_eq!(ones(&rotate(&bits, 100, 1)), vec![69, 98, 99]);
+    assert_eq!(ones(&rotate(&bits, 100, 99)), vec![0, 1, 71]);
+    assert_eq!(ones(&rotate(&bits, 100, 100)), vec![0, 70, 99]);
+    assert_eq!(ones(&rotate(&bits, 100, 65)), vec![5, 34, 35]);
+  }
+
+  #[test]
+  fn test_clear_tiles_match_blizzards() {
+    let grid = lines_to_grid_char_val(&get_example_map());
+    let valley = Valley::parse(&grid);
+    for minute in 0..valley.period() + 1 {
+      let clear = valley.clear(minute);
+      for (y, line) in valley.render(minute, None).lines().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+          assert_eq!(bit(&clear[y], x), c == '.', "minute {} at {},{}", minute, x, y);
+        }
+      }
+    }
   }
 
   #[test]
@@ -358,6 +407,48 @@ mod tests {
     assert_eq!(result, 54)
   }
 
+  #[test]
+  fn test_route_through_waypoints() {
+    let grid = lines_to_grid_char_val(&get_example_map());
+    let (start, end) = get_start_end(&grid);
+    let valley = Valley::parse(&grid);
+    let route = valley.route(&[start, end, start, end], 0).unwrap();
+
+    assert_eq!(route.legs, vec![18, 23, 13]);
+    assert_eq!(route.path.len(), 55);
+    assert_eq!((route.path[0], route.path[18], route.path[41], route.path[54]), (start, end, start, end));
+    for (minute, pair) in route.path.windows(2).enumerate() {
+      assert!((pair[0].x - pair[1].x).abs() + (pair[0].y - pair[1].y).abs() <= 1);
+      assert!(!valley.render(minute + 1, None).lines().nth(pair[1].y as usize).unwrap()[pair[1].x as usize..].starts_with(['<', '>', '^', 'v', '2', '3', '4']));
+    }
+
+    // Waiting at the start for the blizzards to move on is part of the route
+    assert_eq!(route.path[1], start);
+    assert_eq!(valley.render(route.legs[0], route.path.get(route.legs[0]).copied()).lines().last(), Some("######E#"));
+
+    // Setting off later sees the blizzards as they are then, as the last leg
+    // did
+    let later = valley.route(&[start, end], 41).unwrap();
+    assert_eq!(later.legs, vec![13]);
+    assert_eq!(later.path, route.path[41..]);
+  }
+
+  #[test]
+  fn test_route_errors() {
+    let grid = lines_to_grid_char_val(&get_example_map());
+    let (start, _) = get_start_end(&grid);
+    let valley = Valley::parse(&grid);
+    assert_eq!(valley.route(&[start, Point { x: 0, y: 0 }], 0), Err(RouteError::NotOpen(Point { x: 0, y: 0 })));
+
+    // A row that's always full of blizzards can't be crossed
+    let blocked = vec!["#.###".to_string(), "#>>>#".to_string(), "###.#".to_string()];
+    let grid = lines_to_grid_char_val(&blocked);
+    let (start, end) = get_start_end(&grid);
+    let error = Valley::parse(&grid).route(&[start, end], 0).unwrap_err();
+    assert_eq!(error, RouteError::NoPath { leg: 1, from: start, to: end });
+    assert_eq!(error.to_string(), "leg 1 can never get from 1,0 to 3,2");
+  }
+
   fn get_example_map() -> Vec<String> {
     vec![
       "#.######".to_string(),
@@ -15,19 +15,32 @@ mod day11;
 mod day12;
 mod day13;
 mod day14;
+mod day14_scan;
 mod day15;
 mod day16;
 mod day17;
 mod day18;
+mod day18_mesh;
 mod day19;
 mod day20;
 mod day21;
+mod day21_expr;
 mod day22;
+mod day22_cube;
 mod day23;
+mod day23_bitboard;
+mod day23_rules;
 mod day24;
 mod day25;
 
 fn main() {
+  // Any arguments run one of the analysis tools instead of the daily solutions
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  if !args.is_empty() {
+    run_tool(&args);
+    return;
+  }
+
   let day_to_solve = 25;
 
   if day_to_solve == 0 || day_to_solve == 1 {
@@ -202,3 +215,463 @@ fn main() {
     println!("Day 25, part 1: Steps to reach end: {}", solution_day25_part1);
   }
 }
+
+fn run_tool(args: &[String]) {
+  match args[0].as_str() {
+    // day11-report <rounds> <worry divisor> <comma separated rounds to report> [output.csv]
+    "day11-report" => {
+      let rounds = parse_arg(args, 1, "rounds");
+      let worry_divisor = parse_arg(args, 2, "worry divisor");
+      let report_after: Vec<u64> = match args.get(3) {
+        Some(list) => list.split(',').map(|r| r.trim().parse().expect("Rounds to report must be numbers")).collect(),
+        None => (0..=rounds).collect(),
+      };
+      let reports = day11::report(rounds, worry_divisor, &report_after);
+      write_output(args.get(4), &day11::reports_to_csv(&reports));
+    }
+    // day12-path
+    "day12-path" => {
+      match day12::render_shortest_path() {
+        Some(rendered) => println!("{}", rendered),
+        None => println!("No path from S to E"),
+      }
+    }
+    // day12-route <max ascent|any> <max descent|any> <diagonal: true|false> <cost: steps|climb|change>
+    "day12-route" => {
+      let limit = |index: usize, name: &str| match args.get(index).map(|a| a.as_str()) {
+        Some("any") => None,
+        _ => Some(parse_arg(args, index, name)),
+      };
+      let step_cost = match args.get(4).map(|a| a.as_str()) {
+        Some("steps") => day12::StepCost::steps(),
+        Some("climb") => day12::StepCost::total_climb(),
+        Some("change") => day12::StepCost::total_elevation_change(),
+        other => panic!("Unknown step cost: {:?}", other),
+      };
+      let rules = day12::ClimbRules {
+        max_ascent: limit(1, "max ascent"),
+        max_descent: limit(2, "max descent"),
+        diagonal: parse_arg(args, 3, "diagonal"),
+        step_cost,
+        ..day12::ClimbRules::default()
+      };
+      match day12::render_least_cost_route(&rules) {
+        Some((cost, rendered)) => println!("Total cost: {}\n{}", cost, rendered),
+        None => println!("No route from S to E with these rules"),
+      }
+    }
+    // day13-sort <packet file> [output file]
+    "day13-sort" => {
+      let packets = read_packets(args, 1);
+      let sorted: Vec<String> = day13::sort_packets(&packets).iter().map(|p| p.to_string()).collect();
+      write_output(args.get(2), &(sorted.join("\n") + "\n"));
+    }
+    // day13-duplicates <packet file>
+    "day13-duplicates" => {
+      let packets = read_packets(args, 1);
+      let duplicates = day13::find_duplicates(&packets);
+      if duplicates.is_empty() {
+        println!("No duplicate packets");
+      }
+      for (packet, positions) in duplicates {
+        println!("{} appears {} times, at packets {:?}", packet, positions.len(), positions);
+      }
+    }
+    // day13-dividers <packet file> <divider packet>...
+    "day13-dividers" => {
+      let packets = read_packets(args, 1);
+      let dividers = day13::parse_packet_lines(&args[2..]).unwrap_or_else(|e| panic!("Invalid divider packet, {}", e));
+      let indices = day13::divider_indices(&packets, &dividers);
+      for (divider, index) in dividers.iter().zip(&indices) {
+        println!("{} is at index {}", divider, index);
+      }
+      println!("Product of indices: {}", indices.iter().product::<usize>());
+    }
+    // day13-explain <left packet> <right packet>
+    "day13-explain" => {
+      let left: String = parse_arg(args, 1, "left packet");
+      let right: String = parse_arg(args, 2, "right packet");
+      let left: day13::Packet = left.parse().unwrap_or_else(|e| panic!("Invalid left packet, {}", e));
+      let right: day13::Packet = right.parse().unwrap_or_else(|e| panic!("Invalid right packet, {}", e));
+      println!("{}", day13::explain_order(&left, &right));
+    }
+    // day14-sand <abyss|floor:depth> [--trace] [source x,y]...
+    "day14-sand" => {
+      let floor_policy = parse_floor_policy(args, 1);
+      let trace = args.iter().any(|a| a == "--trace");
+      let mut sources: Vec<day14::SandSource> = args[2..]
+        .iter()
+        .filter(|a| *a != "--trace")
+        .map(|a| {
+          let (x, y) = a.split_once(',').expect("Sources must be x,y");
+          day14::SandSource {
+            position: day08::Point { x: x.parse().expect("Bad source x"), y: y.parse().expect("Bad source y") },
+            material: day14::Material::sand(),
+          }
+        })
+        .collect();
+      if sources.is_empty() {
+        sources.push(day14::SandSource::puzzle());
+      }
+
+      let source_count = sources.len();
+      let mut simulation = day14::sand_simulation(sources, floor_policy);
+      if trace {
+        simulation = simulation.with_path_tracking();
+        while (0..source_count).any(|s| !simulation.is_finished(s)) {
+          for source in 0..source_count {
+            let grain = simulation.drop_grain(source);
+            let path: Vec<String> = grain.path.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+            println!("source {}: {:?} via {}", grain.source, grain.outcome, path.join(" -> "));
+          }
+        }
+      } else {
+        simulation.run(None);
+      }
+      day14::print_grid(&simulation.to_grid());
+      println!("Settled grains: {}", simulation.settled_count());
+    }
+    // day14-validate <scan file>
+    "day14-validate" => {
+      let filename: String = parse_arg(args, 1, "scan file");
+      match day14_scan::parse_scan(&utils::read_lines_from_path(&filename)) {
+        Ok(paths) => println!("Scan is valid: {} rock paths", paths.len()),
+        Err(errors) => errors.iter().for_each(|e| println!("{}", e)),
+      }
+    }
+    // day14-export <scan file> <abyss|floor:depth> <output .txt or .ppm>
+    "day14-export" => {
+      let filename: String = parse_arg(args, 1, "scan file");
+      let floor_policy = parse_floor_policy(args, 2);
+      let output: String = parse_arg(args, 3, "output file");
+      let paths = day14_scan::parse_scan(&utils::read_lines_from_path(&filename))
+        .unwrap_or_else(|errors| panic!("Invalid cave scan, first problem {}", errors[0]));
+      let mut simulation = day14::SandSimulation::new(&day14_scan::paths_to_grid(&paths), vec![day14::SandSource::puzzle()], floor_policy);
+      simulation.run(None);
+      let cave = simulation.to_grid();
+      if output.ends_with(".ppm") {
+        std::fs::write(&output, day14_scan::cave_to_ppm(&cave)).expect("Unable to write output file");
+      } else {
+        write_output(Some(&output), &day14_scan::cave_to_text(&cave));
+      }
+      println!("Settled grains: {}", simulation.settled_count());
+    }
+    // day14-generate <width> <height> <path count> <seed> [output file]
+    "day14-generate" => {
+      let scan = day14_scan::generate_scan(
+        parse_arg(args, 1, "width"),
+        parse_arg(args, 2, "height"),
+        parse_arg(args, 3, "path count"),
+        parse_arg(args, 4, "seed"),
+      );
+      write_output(args.get(5), &(scan.join("\n") + "\n"));
+    }
+    // day17-events <rocks> [shapes file] [width] [spawn left] [spawn gap]
+    "day17-events" => {
+      let rocks = parse_arg(args, 1, "rocks");
+      let shapes = match args.get(2) {
+        Some(filename) => day17::parse_shapes(&std::fs::read_to_string(filename).expect("Unable to read shapes file"))
+          .unwrap_or_else(|e| panic!("Invalid shapes file, {}", e)),
+        None => day17::default_shapes(),
+      };
+      let defaults = day17::ChamberConfig::default();
+      let config = day17::ChamberConfig {
+        width: if args.len() > 3 { parse_arg(args, 3, "width") } else { defaults.width },
+        spawn_left: if args.len() > 4 { parse_arg(args, 4, "spawn left") } else { defaults.spawn_left },
+        spawn_gap: if args.len() > 5 { parse_arg(args, 5, "spawn gap") } else { defaults.spawn_gap },
+      };
+      let jets = day17::parse_input(&utils::read_chunks("day17.txt", "\n"));
+      let mut chamber = day17::Chamber::new(shapes, Vec::from(jets), config);
+
+      println!("rock,shape,x,y,first_jet,jets_used,height");
+      for event in chamber.drop_rocks(rocks) {
+        println!("{},{},{},{},{},{},{}", event.rock, event.shape, event.x, event.y, event.first_jet, event.jets_used, event.height);
+      }
+    }
+    // day17-tower <rocks>
+    "day17-tower" => {
+      let jets = day17::parse_input(&utils::read_chunks("day17.txt", "\n"));
+      let mut chamber = day17::Chamber::new(day17::default_shapes(), Vec::from(jets), day17::ChamberConfig::default());
+      chamber.drop_rocks(parse_arg(args, 1, "rocks"));
+      println!("{}", chamber.render());
+    }
+    // day17-height <rocks>
+    "day17-height" => {
+      let heights = day17::tower_heights();
+      match heights.cycle() {
+        Some(cycle) => println!("Cycle of {} rocks after the first {}, growing {} rows each time", cycle.period, cycle.offset, cycle.height_per_period),
+        None => println!("No cycle found"),
+      }
+      match heights.height_after(parse_arg(args, 1, "rocks")) {
+        Some(height) => println!("Height: {}", height),
+        None => println!("Height unknown, too many rocks to simulate without a cycle"),
+      }
+    }
+    // day18-report
+    "day18-report" => {
+      let report = day18::voxel_report();
+      println!("Lava volume: {}", report.lava_volume);
+      println!("Enclosed volume: {}", report.enclosed_volume);
+      println!("Surface area: {} ({} exterior, {} interior)", report.surface_area, report.exterior_surface_area, report.interior_surface_area);
+      println!("Droplets: {}", report.droplets.len());
+      println!("Air pockets: {}", report.air_pockets.len());
+      for pocket in &report.air_pockets {
+        println!(
+          "  volume {} from {},{},{} to {},{},{}",
+          pocket.volume, pocket.min.x, pocket.min.y, pocket.min.z, pocket.max.x, pocket.max.y, pocket.max.z
+        );
+      }
+    }
+    // day18-mesh <obj|stl> [output file] [--merge]
+    "day18-mesh" => {
+      let merge = args.iter().any(|a| a == "--merge");
+      let args: Vec<String> = args.iter().filter(|a| !a.starts_with("--")).cloned().collect();
+      let format: String = parse_arg(&args, 1, "format");
+      let quads = day18_mesh::exterior_quads(&day18::voxel_grid(), merge);
+      let mesh = match format.as_str() {
+        "obj" => day18_mesh::to_obj(&quads),
+        "stl" => day18_mesh::to_stl(&quads),
+        other => panic!("Unknown mesh format: {}", other),
+      };
+      write_output(args.get(2), &mesh);
+      eprintln!("{} quads covering {} cube faces", quads.len(), quads.iter().map(|q| q.area()).sum::<i64>());
+    }
+    // day19-report [minutes] [blueprint id to show the build order of] [--target <resource>] [--input <file>]
+    "day19-report" => {
+      let flag = |name: &str| {
+        args.iter().position(|a| a == name).map(|i| {
+          args.get(i + 1).unwrap_or_else(|| panic!("Missing value for {}", name)).clone()
+        })
+      };
+      let target = flag("--target").unwrap_or_else(|| "geode".to_string());
+      let input = flag("--input").unwrap_or_else(|| "inputs/day19.txt".to_string());
+      let args: Vec<String> = args.iter().take_while(|a| !a.starts_with("--")).cloned().collect();
+
+      let minutes = args.get(1).map_or(24, |_| parse_arg(&args, 1, "minutes"));
+      let plans = day19::blueprint_plans(&utils::read_lines_from_path(&input), &target, minutes);
+      print!("{}", day19::plans_to_table(&plans));
+      if args.len() > 2 {
+        let id: usize = parse_arg(&args, 2, "blueprint id");
+        let plan = plans.iter().find(|p| p.id == id).expect("No blueprint with that id");
+        println!();
+        print!("{}", day19::build_order_to_text(plan));
+      }
+    }
+    // day20-mix <input file> <rounds> <decryption key> [output file] [--positions]
+    "day20-mix" => {
+      let positions = args.iter().any(|a| a == "--positions");
+      let args: Vec<String> = args.iter().filter(|a| !a.starts_with("--")).cloned().collect();
+      let filename: String = parse_arg(&args, 1, "input file");
+      let numbers = day20::parse_input(&utils::read_lines_from_path(&filename));
+      let rounds: usize = parse_arg(&args, 2, "rounds");
+      let key: i64 = parse_arg(&args, 3, "decryption key");
+      let mixed = day20::mix(&numbers, rounds, key);
+
+      // With --positions each line also says where the number started out,
+      // which is what day20-unmix needs
+      let lines: Vec<String> = match positions {
+        true => mixed.original_positions().iter().zip(mixed.values()).map(|(p, n)| format!("{} {}\n", p, n)).collect(),
+        false => mixed.values().iter().map(|n| format!("{}\n", n)).collect(),
+      };
+      write_output(args.get(4), &lines.concat());
+      eprintln!("Grove coordinates: {}", mixed.grove_coordinates(&day20::GROVE_OFFSETS));
+    }
+    // day20-unmix <mixed file with positions> <rounds> <decryption key> [output file]
+    "day20-unmix" => {
+      let filename: String = parse_arg(args, 1, "mixed file");
+      let (positions, mixed): (Vec<usize>, Vec<i64>) = utils::read_lines_from_path(&filename)
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+          let (position, number) = l.trim().split_once(' ').expect("Expected lines like '<position> <number>'");
+          (position.parse::<usize>().expect("Invalid position"), number.parse::<i64>().expect("Invalid number"))
+        })
+        .unzip();
+      let rounds: usize = parse_arg(args, 2, "rounds");
+      let key: i64 = parse_arg(args, 3, "decryption key");
+      let original = day20::unmix(&mixed, &positions, rounds, key);
+      let lines: Vec<String> = original.iter().map(|n| format!("{}\n", n)).collect();
+      write_output(args.get(4), &lines.concat());
+    }
+    // day20-verify <original file> <mixed file> <rounds> <decryption key>
+    "day20-verify" => {
+      let original_file: String = parse_arg(args, 1, "original file");
+      let mixed_file: String = parse_arg(args, 2, "mixed file");
+      let original = day20::parse_input(&utils::read_lines_from_path(&original_file));
+      let claimed = day20::parse_input(&utils::read_lines_from_path(&mixed_file));
+      let rounds: usize = parse_arg(args, 3, "rounds");
+      let key: i64 = parse_arg(args, 4, "decryption key");
+      match day20::verify_mixed(&original, &claimed, rounds, key) {
+        Ok(()) => println!("Mixed file matches"),
+        Err(mismatch) => {
+          println!("Mixed file doesn't match: {}", mismatch);
+          std::process::exit(1);
+        }
+      }
+    }
+    // day21-graph <input file> [output.dot]
+    "day21-graph" => {
+      let filename: String = parse_arg(args, 1, "input file");
+      let monkeys = day21::parse_input(&utils::read_lines_from_path(&filename));
+      let checked = day21::parse_jobs(&monkeys).and_then(|jobs| day21::dependency_order(&jobs, "root").map(|order| (jobs, order.len())));
+      match checked {
+        Ok((jobs, needed)) => {
+          write_output(args.get(2), &day21::to_dot(&jobs, "root", "humn"));
+          eprintln!("{} monkeys, {} needed by root", jobs.len(), needed);
+        }
+        Err(e) => {
+          eprintln!("Invalid monkeys: {}", e);
+          std::process::exit(1);
+        }
+      }
+    }
+    // day21-solve <input file> [equal|difference]
+    "day21-solve" => {
+      let filename: String = parse_arg(args, 1, "input file");
+      let monkeys = day21::parse_input(&utils::read_lines_from_path(&filename));
+      let comparison = match args.get(2) {
+        Some(c) => day21_expr::Comparison::parse(c).unwrap_or_else(|| panic!("Unknown comparison: {}", c)),
+        None => day21_expr::Comparison::Equal,
+      };
+      match day21::humn_values(&monkeys, comparison) {
+        Ok(values) => values.iter().for_each(|v| println!("{}", v)),
+        Err(e) => {
+          eprintln!("Unable to find humn: {}", e);
+          std::process::exit(1);
+        }
+      }
+    }
+    // day22-trail <flat|cube|torus|mobius> [output file]
+    "day22-trail" => {
+      let (grid, moves) = day22::read_input();
+      let topology = day22::topology(&parse_arg::<String>(args, 1, "topology"), &grid);
+      let walked = day22::walk(&grid, &moves, topology.as_ref());
+      write_output(args.get(2), &day22::render_trail(&grid, &walked.trail));
+    }
+    // day22-replay <flat|cube|torus|mobius>
+    "day22-replay" => {
+      let (grid, moves) = day22::read_input();
+      let topology = day22::topology(&parse_arg::<String>(args, 1, "topology"), &grid);
+      let walked = day22::walk(&grid, &moves, topology.as_ref());
+      print!("{}", day22::replay_to_text(&walked.replay));
+      let (position, direction) = walked.trail.last().unwrap();
+      println!("Password: {}", day22::compute_password(*position, direction.clone()));
+    }
+    // day23-compare <input> <max rounds>
+    "day23-compare" => {
+      let filename: String = parse_arg(args, 1, "input file");
+      let max_rounds: usize = parse_arg(args, 2, "max rounds");
+      let grid = day12::lines_to_grid_char_val(&utils::read_lines_from_path(&filename));
+      let (rounds, map_time, board_time) = day23::compare_engines(&grid, max_rounds);
+      println!("{} rounds match: map {:?}, bitboard {:?}", rounds, map_time, board_time);
+    }
+    // day23-rules <input> <square|hex|cube> [rounds] [--order N,S,W,E] [--rotate every:<n>|never]
+    //   [--min-neighbours <n>] [--front-only]
+    "day23-rules" => {
+      let filename: String = parse_arg(args, 1, "input file");
+      let neighbourhood: String = parse_arg(args, 2, "neighbourhood");
+      let mut rules = day23_rules::Rules::by_name(&neighbourhood).unwrap_or_else(|| panic!("Unknown neighbourhood: {}", neighbourhood));
+      let mut rounds = 10;
+      let mut index = 3;
+      while index < args.len() {
+        match args[index].as_str() {
+          "--order" => {
+            let order: String = parse_arg(args, index + 1, "proposal order");
+            let names: Vec<&str> = order.split(',').collect();
+            rules = rules.with_order(&names).unwrap_or_else(|e| panic!("Invalid order, {}", e));
+            index += 1;
+          }
+          "--rotate" => {
+            let rotation: String = parse_arg(args, index + 1, "rotation");
+            rules.rotation = day23_rules::Rotation::parse(&rotation).unwrap_or_else(|| panic!("Unknown rotation: {}", rotation));
+            index += 1;
+          }
+          "--min-neighbours" => {
+            rules.min_neighbours = parse_arg(args, index + 1, "minimum neighbours");
+            index += 1;
+          }
+          "--front-only" => rules = rules.front_only(),
+          _ => rounds = parse_arg(args, index, "rounds"),
+        }
+        index += 1;
+      }
+      let grid = day12::lines_to_grid_char_val(&utils::read_lines_from_path(&filename));
+      let stats = day23_rules::simulate(&day23_rules::elves_from_grid(&grid), &rules, rounds);
+      print!("{}", day23_rules::stats_to_table(&stats));
+    }
+    // day24-route <input> <start|end|x,y>... [--at <minute>] [--draw]
+    "day24-route" => {
+      let filename: String = parse_arg(args, 1, "input file");
+      let grid = day12::lines_to_grid_char_val(&utils::read_lines_from_path(&filename));
+      let (start, end) = day24::get_start_end(&grid);
+      let mut waypoints = vec![];
+      let (mut start_minute, mut draw) = (0, false);
+      let mut index = 2;
+      while index < args.len() {
+        match args[index].as_str() {
+          "--at" => {
+            start_minute = parse_arg(args, index + 1, "start minute");
+            index += 1;
+          }
+          "--draw" => draw = true,
+          "start" => waypoints.push(start),
+          "end" => waypoints.push(end),
+          waypoint => {
+            let (x, y) = waypoint.split_once(',').unwrap_or_else(|| panic!("Invalid waypoint: {}", waypoint));
+            let coordinate = |c: &str| c.parse().unwrap_or_else(|_| panic!("Invalid waypoint: {}", waypoint));
+            waypoints.push(day08::Point { x: coordinate(x), y: coordinate(y) });
+          }
+        }
+        index += 1;
+      }
+
+      let valley = day24::Valley::parse(&grid);
+      let route = valley.route(&waypoints, start_minute).unwrap_or_else(|e| panic!("No route: {}", e));
+      if draw {
+        for (i, position) in route.path.iter().enumerate() {
+          println!("Minute {}", route.start_minute + i);
+          println!("{}", valley.render(route.start_minute + i, Some(*position)));
+        }
+      }
+      for (leg, minutes) in route.legs.iter().enumerate() {
+        println!("Leg {}: {} minutes", leg + 1, minutes);
+      }
+      println!("Total: {} minutes", route.total());
+    }
+    other => panic!("Unknown tool: {}", other),
+  }
+}
+
+fn parse_floor_policy(args: &[String], index: usize) -> day14::FloorPolicy {
+  match args.get(index).map(|a| a.as_str()) {
+    Some("abyss") => day14::FloorPolicy::Abyss,
+    Some(floor) if floor.starts_with("floor:") => {
+      let depth: i64 = floor["floor:".len()..].parse().expect("Floor depth must be a number");
+      if depth < 1 {
+        panic!("Floor depth must be at least 1, not {}", depth);
+      }
+      day14::FloorPolicy::Floor { depth }
+    }
+    other => panic!("Unknown floor policy: {:?}", other),
+  }
+}
+
+fn read_packets(args: &[String], index: usize) -> Vec<day13::Packet> {
+  let filename: String = parse_arg(args, index, "packet file");
+  day13::parse_packet_lines(&utils::read_lines_from_path(&filename)).unwrap_or_else(|e| panic!("Invalid packet file, {}", e))
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, name: &str) -> T {
+  args
+    .get(index)
+    .unwrap_or_else(|| panic!("Missing argument: {}", name))
+    .parse()
+    .unwrap_or_else(|_| panic!("Unable to parse argument: {}", name))
+}
+
+fn write_output(filename: Option<&String>, contents: &str) {
+  match filename {
+    Some(f) => std::fs::write(f, contents).expect("Unable to write output file"),
+    None => print!("{}", contents),
+  }
+}
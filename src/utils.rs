@@ -33,6 +33,14 @@ pub fn read_chunks(filename: &str, splitter: &str) -> Vec<String> {
     .collect()
 }
 
+// For tools that take any file rather than one of the puzzle inputs
+pub fn read_lines_from_path(path: &str) -> Vec<String> {
+  match fs::read_to_string(path) {
+    Err(error) => panic!("Unable to read file {:?}", error),
+    Ok(s) => s.lines().map(String::from).collect()
+  }
+}
+
 fn read_input(filename: &str) -> String {
   let file_path = format!("inputs/{}", filename);
   let input_string = fs::read_to_string(file_path);